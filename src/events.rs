@@ -6,6 +6,8 @@ use libc::{ c_int, c_float, c_double, c_uint };
 use ffi::*;
 use Game;
 
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Event {
     Close,
     Focus,
@@ -23,8 +25,19 @@ pub enum Event {
     KeyRelease(Option<Key>, Modifiers, i32),
     Character(char, Modifiers),
     // Files(Vec<String>),
-    // GamepadConnected(GamepadHandle),
-    // GamepadDisconnected(GamepadHandle)
+    GamepadConnected(GamepadHandle),
+    GamepadDisconnected(GamepadHandle),
+    GamepadPress(GamepadHandle, GamepadButton),
+    GamepadRelease(GamepadHandle, GamepadButton),
+    GamepadAxis(GamepadHandle, GamepadAxis, f32)
+}
+
+/// An `Event` along with the time it was received, on the same clock used to compute frame deltas.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimedEvent {
+    pub time: f64,
+    pub event: Event
 }
 
 enum InternalEvent {
@@ -32,35 +45,91 @@ enum InternalEvent {
     Refresh
 }
 
-pub(crate) struct Target<'a> {
-    pub game: Box<Game + 'a>,
+pub(crate) struct Target<'a, G: Game + ?Sized + 'a> {
+    pub game: Box<G>,
     pub ctx: &'a ::gfx::Context,
-    pub queue: Vec<Event>,
+    pub queue: Vec<TimedEvent>,
+    pub gamepads: [Option<GLFWgamepadstate>; GLFW_JOYSTICK_LAST as usize + 1],
     pub polling: bool,
     pub width: u32,
     pub height: u32,
 }
 
+impl<'a, G: Game + ?Sized + 'a> Target<'a, G> {
+    pub fn push(&mut self, event: Event, time: f64) {
+        self.queue.push(TimedEvent { time: time, event: event });
+    }
+
+    pub fn poll_gamepads(&mut self) {
+        let time = unsafe { glfwGetTime() };
+        for jid in 0..self.gamepads.len() {
+            let handle = GamepadHandle { jid: jid as c_int };
+            let mut state: GLFWgamepadstate = unsafe { mem::zeroed() };
+            let present = unsafe { glfwGetGamepadState(jid as c_int, &mut state) } != 0;
+            match (self.gamepads[jid], present) {
+                (None, false) => {},
+                (Some(_), false) => self.push(Event::GamepadDisconnected(handle), time),
+                (previous, true) => {
+                    if previous.is_none() {
+                        self.push(Event::GamepadConnected(handle), time);
+                    }
+                    let previous = previous.unwrap_or(unsafe { mem::zeroed() });
+                    for (i, (&old, &new)) in previous.buttons.iter().zip(state.buttons.iter()).enumerate() {
+                        let button = unsafe { mem::transmute(i as i32) };
+                        if new == GLFW_PRESS as u8 && old != GLFW_PRESS as u8 {
+                            self.push(Event::GamepadPress(handle, button), time);
+                        } else if new != GLFW_PRESS as u8 && old == GLFW_PRESS as u8 {
+                            self.push(Event::GamepadRelease(handle, button), time);
+                        }
+                    }
+                    for i in 0..state.axes.len() {
+                        let (old, new) = (previous.axes[i], state.axes[i]);
+                        if axis_moved(old, new) {
+                            self.push(Event::GamepadAxis(handle, unsafe { mem::transmute(i as i32) }, new), time);
+                        } else {
+                            // Compare against the last reported value so slow drift still adds up.
+                            state.axes[i] = old;
+                        }
+                    }
+                }
+            }
+            self.gamepads[jid] = if present { Some(state) } else { None };
+        }
+    }
+
+    pub fn dispatch_queued(&mut self) {
+        for e in self.queue.drain(0..) {
+            if let Event::Resize(width, height) = e.event {
+                self.width = width;
+                self.height = height;
+            }
+            self.game.timed_event(e);
+        }
+    }
+}
+
+/// Axis movements smaller than this are not reported, so analog stick noise doesn't flood the
+/// event queue.
+const AXIS_THRESHOLD: f32 = 0.01;
+
+fn axis_moved(old: f32, new: f32) -> bool {
+    (new - old).abs() >= AXIS_THRESHOLD || (new != old && new.abs() >= 1.0)
+}
+
 fn send(window: *mut GLFWwindow, event: InternalEvent) {
     let p = unsafe { glfwGetWindowUserPointer(window) };
     if p.is_null() { return; }
-    let target = unsafe { &mut *(p as *mut Target) };
-    if target.polling {
-        match event {
-            InternalEvent::User(e) => {
-                if let Event::Resize(width, height) = e {
-                    target.width = width;
-                    target.height = height;
-                }
-                target.game.event(e);
-            },
-            InternalEvent::Refresh => {
-                target.game.frame(target.ctx.create_screen_surface(target.width, target.height), 0.0);
-                unsafe { glfwSwapBuffers(window) };
-            }
+    let target = unsafe { &mut *(p as *mut Target<Game>) };
+    let time = unsafe { glfwGetTime() };
+    match event {
+        InternalEvent::User(e) => target.push(e, time),
+        InternalEvent::Refresh => if target.polling {
+            let mut width = 0;
+            let mut height = 0;
+            unsafe { glfwGetFramebufferSize(window, &mut width, &mut height) };
+            target.game.redraw(target.ctx.create_screen_surface(width as u32, height as u32));
+            unsafe { glfwSwapBuffers(window) };
         }
-    } else if let InternalEvent::User(e) = event {
-        target.queue.push(e);
     }
 }
 
@@ -126,13 +195,62 @@ pub extern "C" fn char_mods(window: *mut GLFWwindow, codepoint: c_uint, mods: c_
     send(window, InternalEvent::User(Event::Character(char::from_u32(codepoint).unwrap_or(char::REPLACEMENT_CHARACTER), Modifiers::from_bits(mods).unwrap())));
 }
 
-// pub struct GamepadHandle {
-//     jid: c_int
-// }
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GamepadHandle {
+    pub(crate) jid: c_int
+}
+
+impl GamepadHandle {
+    pub fn from_index(index: u32) -> Self {
+        GamepadHandle { jid: index as c_int }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.jid as u32
+    }
+}
+
+enum_from_primitive! {
+    #[repr(i32)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum GamepadButton {
+        A = GLFW_GAMEPAD_BUTTON_A,
+        B = GLFW_GAMEPAD_BUTTON_B,
+        X = GLFW_GAMEPAD_BUTTON_X,
+        Y = GLFW_GAMEPAD_BUTTON_Y,
+        LeftBumper = GLFW_GAMEPAD_BUTTON_LEFT_BUMPER,
+        RightBumper = GLFW_GAMEPAD_BUTTON_RIGHT_BUMPER,
+        Back = GLFW_GAMEPAD_BUTTON_BACK,
+        Start = GLFW_GAMEPAD_BUTTON_START,
+        Guide = GLFW_GAMEPAD_BUTTON_GUIDE,
+        LeftThumb = GLFW_GAMEPAD_BUTTON_LEFT_THUMB,
+        RightThumb = GLFW_GAMEPAD_BUTTON_RIGHT_THUMB,
+        DpadUp = GLFW_GAMEPAD_BUTTON_DPAD_UP,
+        DpadRight = GLFW_GAMEPAD_BUTTON_DPAD_RIGHT,
+        DpadDown = GLFW_GAMEPAD_BUTTON_DPAD_DOWN,
+        DpadLeft = GLFW_GAMEPAD_BUTTON_DPAD_LEFT
+    }
+}
+enum_from_primitive! {
+    #[repr(i32)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub enum GamepadAxis {
+        LeftX = GLFW_GAMEPAD_AXIS_LEFT_X,
+        LeftY = GLFW_GAMEPAD_AXIS_LEFT_Y,
+        RightX = GLFW_GAMEPAD_AXIS_RIGHT_X,
+        RightY = GLFW_GAMEPAD_AXIS_RIGHT_Y,
+        LeftTrigger = GLFW_GAMEPAD_AXIS_LEFT_TRIGGER,
+        RightTrigger = GLFW_GAMEPAD_AXIS_RIGHT_TRIGGER
+    }
+}
 
 enum_from_primitive! {
     #[repr(i32)]
     #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub enum MouseButton {
         Left = GLFW_MOUSE_BUTTON_LEFT,
         Right = GLFW_MOUSE_BUTTON_RIGHT,
@@ -147,6 +265,7 @@ enum_from_primitive! {
 enum_from_primitive! {
     #[repr(i32)]
     #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub enum Key {
         Unknown = GLFW_KEY_UNKNOWN,
         Space = GLFW_KEY_SPACE,
@@ -274,6 +393,7 @@ enum_from_primitive! {
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct Modifiers: c_int {
         const SHIFT = GLFW_MOD_SHIFT;
         const CONTROL = GLFW_MOD_CONTROL;
@@ -376,9 +376,10 @@ pub struct GLFWimage {
 }
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct GLFWgamepadstate {
-    buttons: [c_uchar; 15],
-    axes: [c_float; 6]
+    pub buttons: [c_uchar; 15],
+    pub axes: [c_float; 6]
 }
 
 #[link(name = "glfw3", kind = "static")]
@@ -0,0 +1,439 @@
+use std::ffi::{ CStr, CString };
+use std::os::raw::c_void;
+use std::ptr;
+
+use gl::types::*;
+use gl;
+
+use gfx::backend::Backend;
+
+/// Calls the OpenGL functions loaded for the current context.
+pub struct GlBackend;
+
+fn data_ptr(data: Option<&[u8]>) -> *const GLvoid {
+    data.map_or(ptr::null(), |d| d.as_ptr() as *const GLvoid)
+}
+
+/// The size in bytes of one pixel in client memory.
+fn pixel_size(format: GLenum, gltype: GLenum) -> usize {
+    let components = match format {
+        gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT | gl::STENCIL_INDEX | gl::DEPTH_STENCIL => 1,
+        gl::RG | gl::RG_INTEGER => 2,
+        gl::RGB | gl::BGR | gl::RGB_INTEGER => 3,
+        _ => 4
+    };
+    match gltype {
+        gl::UNSIGNED_BYTE | gl::BYTE => components,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => components * 2,
+        gl::FLOAT_32_UNSIGNED_INT_24_8_REV => 8,
+        _ => components * 4
+    }
+}
+
+fn check_image_size(len: usize, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum) {
+    assert!(width >= 0 && height >= 0, "Invalid image size {}x{}", width, height);
+    let needed = width as usize * height as usize * pixel_size(format, gltype);
+    assert!(len >= needed, "{}x{} pixels need {} bytes, but only {} were given", width, height, needed, len);
+}
+
+fn clear_value_len(buffer: GLenum) -> usize {
+    if buffer == gl::COLOR { 4 } else { 1 }
+}
+
+fn c_string(s: &str) -> CString {
+    CString::new(s).expect("GL strings cannot contain nul bytes")
+}
+
+unsafe fn from_gl_string(s: *const GLubyte) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s as *const _).to_string_lossy().into_owned()
+    }
+}
+
+impl Backend for GlBackend {
+    fn get_error(&self) -> GLenum {
+        unsafe { gl::GetError() }
+    }
+
+    fn get_integer(&self, pname: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetIntegerv(pname, &mut value) };
+        value
+    }
+
+    fn get_string(&self, name: GLenum) -> String {
+        unsafe { from_gl_string(gl::GetString(name)) }
+    }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> String {
+        unsafe { from_gl_string(gl::GetStringi(name, index)) }
+    }
+
+    fn enable(&self, cap: GLenum) {
+        unsafe { gl::Enable(cap) }
+    }
+
+    fn disable(&self, cap: GLenum) {
+        unsafe { gl::Disable(cap) }
+    }
+
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        unsafe { gl::Viewport(x, y, width, height) }
+    }
+
+    fn scissor(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        unsafe { gl::Scissor(x, y, width, height) }
+    }
+
+    fn gen_buffer(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, &mut id) };
+        id
+    }
+
+    fn delete_buffer(&self, id: GLuint) {
+        unsafe { gl::DeleteBuffers(1, &id) }
+    }
+
+    fn bind_buffer(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BindBuffer(target, id) }
+    }
+
+    fn buffer_data(&self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum) {
+        if let Some(d) = data {
+            assert!(d.len() == size, "Buffer data is {} bytes, expected {}", d.len(), size);
+        }
+        unsafe { gl::BufferData(target, size as GLsizeiptr, data_ptr(data), usage) }
+    }
+
+    fn buffer_sub_data(&self, target: GLenum, offset: usize, data: &[u8]) {
+        unsafe { gl::BufferSubData(target, offset as GLintptr, data.len() as GLsizeiptr, data.as_ptr() as *const GLvoid) }
+    }
+
+    fn copy_buffer_sub_data(&self, read_target: GLenum, write_target: GLenum, read_offset: usize, write_offset: usize, size: usize) {
+        unsafe {
+            gl::CopyBufferSubData(
+                read_target, write_target, read_offset as GLintptr, write_offset as GLintptr, size as GLsizeiptr
+            )
+        }
+    }
+
+    fn gen_vertex_array(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenVertexArrays(1, &mut id) };
+        id
+    }
+
+    fn delete_vertex_array(&self, id: GLuint) {
+        unsafe { gl::DeleteVertexArrays(1, &id) }
+    }
+
+    fn bind_vertex_array(&self, id: GLuint) {
+        unsafe { gl::BindVertexArray(id) }
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, gltype: GLenum, normalized: bool, stride: GLsizei, offset: usize) {
+        let normalized = if normalized { gl::TRUE } else { gl::FALSE };
+        unsafe { gl::VertexAttribPointer(index, size, gltype, normalized, stride, offset as *const GLvoid) }
+    }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        unsafe { gl::EnableVertexAttribArray(index) }
+    }
+
+    fn gen_texture(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenTextures(1, &mut id) };
+        id
+    }
+
+    fn delete_texture(&self, id: GLuint) {
+        unsafe { gl::DeleteTextures(1, &id) }
+    }
+
+    fn active_texture(&self, unit: GLuint) {
+        unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) }
+    }
+
+    fn bind_texture(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BindTexture(target, id) }
+    }
+
+    fn tex_image_2d(&self, internal_format: GLenum, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, data: Option<&[u8]>) {
+        if let Some(d) = data {
+            check_image_size(d.len(), width, height, format, gltype);
+        }
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format as GLint, width, height, 0, format, gltype, data_ptr(data))
+        }
+    }
+
+    fn tex_parameter_i(&self, pname: GLenum, value: GLint) {
+        unsafe { gl::TexParameteri(gl::TEXTURE_2D, pname, value) }
+    }
+
+    fn gen_framebuffer(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenFramebuffers(1, &mut id) };
+        id
+    }
+
+    fn delete_framebuffer(&self, id: GLuint) {
+        unsafe { gl::DeleteFramebuffers(1, &id) }
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BindFramebuffer(target, id) }
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture: GLuint) {
+        unsafe { gl::FramebufferTexture2D(target, attachment, gl::TEXTURE_2D, texture, 0) }
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint) {
+        unsafe { gl::FramebufferRenderbuffer(target, attachment, gl::RENDERBUFFER, renderbuffer) }
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        unsafe { gl::CheckFramebufferStatus(target) }
+    }
+
+    fn draw_buffer(&self, buffer: GLenum) {
+        unsafe { gl::DrawBuffer(buffer) }
+    }
+
+    fn draw_buffers(&self, buffers: &[GLenum]) {
+        unsafe { gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr()) }
+    }
+
+    fn read_buffer(&self, buffer: GLenum) {
+        unsafe { gl::ReadBuffer(buffer) }
+    }
+
+    fn blit_framebuffer(&self, src: [GLint; 4], dst: [GLint; 4], mask: GLbitfield, filter: GLenum) {
+        unsafe {
+            gl::BlitFramebuffer(src[0], src[1], src[2], src[3], dst[0], dst[1], dst[2], dst[3], mask, filter)
+        }
+    }
+
+    fn clear_buffer_fv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLfloat]) {
+        assert!(value.len() >= clear_value_len(buffer), "Not enough components to clear 0x{:X}", buffer);
+        unsafe { gl::ClearBufferfv(buffer, draw_buffer, value.as_ptr()) }
+    }
+
+    fn clear_buffer_iv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLint]) {
+        assert!(value.len() >= clear_value_len(buffer), "Not enough components to clear 0x{:X}", buffer);
+        unsafe { gl::ClearBufferiv(buffer, draw_buffer, value.as_ptr()) }
+    }
+
+    fn clear_buffer_uiv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLuint]) {
+        assert!(value.len() >= clear_value_len(buffer), "Not enough components to clear 0x{:X}", buffer);
+        unsafe { gl::ClearBufferuiv(buffer, draw_buffer, value.as_ptr()) }
+    }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, into: &mut [u8]) {
+        check_image_size(into.len(), width, height, format, gltype);
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(x, y, width, height, format, gltype, into.as_mut_ptr() as *mut GLvoid)
+        }
+    }
+
+    fn gen_renderbuffer(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenRenderbuffers(1, &mut id) };
+        id
+    }
+
+    fn delete_renderbuffer(&self, id: GLuint) {
+        unsafe { gl::DeleteRenderbuffers(1, &id) }
+    }
+
+    fn bind_renderbuffer(&self, id: GLuint) {
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, id) }
+    }
+
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei) {
+        unsafe { gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples, format, width, height) }
+    }
+
+    fn get_renderbuffer_samples(&self) -> GLint {
+        let mut samples = 0;
+        unsafe { gl::GetRenderbufferParameteriv(gl::RENDERBUFFER, gl::RENDERBUFFER_SAMPLES, &mut samples) };
+        samples
+    }
+
+    fn create_shader(&self, shader_type: GLenum) -> GLuint {
+        unsafe { gl::CreateShader(shader_type) }
+    }
+
+    fn delete_shader(&self, id: GLuint) {
+        unsafe { gl::DeleteShader(id) }
+    }
+
+    fn shader_source(&self, id: GLuint, source: &str) {
+        let source = c_string(source);
+        let p = source.as_ptr();
+        unsafe { gl::ShaderSource(id, 1, &p, ptr::null()) }
+    }
+
+    fn compile_shader(&self, id: GLuint) {
+        unsafe { gl::CompileShader(id) }
+    }
+
+    fn get_compile_status(&self, id: GLuint) -> bool {
+        let mut status = 0;
+        unsafe { gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut status) };
+        status != gl::FALSE as GLint
+    }
+
+    fn get_shader_info_log(&self, id: GLuint) -> String {
+        let mut length = 0;
+        unsafe { gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut length) };
+        let mut log = vec![0u8; length.max(1) as usize];
+        unsafe { gl::GetShaderInfoLog(id, length, ptr::null_mut(), log.as_mut_ptr() as *mut GLchar) };
+        info_log_to_string(log)
+    }
+
+    fn create_program(&self) -> GLuint {
+        unsafe { gl::CreateProgram() }
+    }
+
+    fn delete_program(&self, id: GLuint) {
+        unsafe { gl::DeleteProgram(id) }
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        unsafe { gl::AttachShader(program, shader) }
+    }
+
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str) {
+        unsafe { gl::BindFragDataLocation(program, color, c_string(name).as_ptr()) }
+    }
+
+    fn link_program(&self, id: GLuint) {
+        unsafe { gl::LinkProgram(id) }
+    }
+
+    fn get_link_status(&self, id: GLuint) -> bool {
+        let mut status = 0;
+        unsafe { gl::GetProgramiv(id, gl::LINK_STATUS, &mut status) };
+        status != gl::FALSE as GLint
+    }
+
+    fn get_program_info_log(&self, id: GLuint) -> String {
+        let mut length = 0;
+        unsafe { gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut length) };
+        let mut log = vec![0u8; length.max(1) as usize];
+        unsafe { gl::GetProgramInfoLog(id, length, ptr::null_mut(), log.as_mut_ptr() as *mut GLchar) };
+        info_log_to_string(log)
+    }
+
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint {
+        unsafe { gl::GetAttribLocation(program, c_string(name).as_ptr()) }
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint {
+        unsafe { gl::GetUniformLocation(program, c_string(name).as_ptr()) }
+    }
+
+    fn use_program(&self, id: GLuint) {
+        unsafe { gl::UseProgram(id) }
+    }
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat) {
+        unsafe { gl::Uniform1f(location, x) }
+    }
+
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat) {
+        unsafe { gl::Uniform2f(location, x, y) }
+    }
+
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat) {
+        unsafe { gl::Uniform3f(location, x, y, z) }
+    }
+
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        unsafe { gl::Uniform4f(location, x, y, z, w) }
+    }
+
+    fn uniform_1i(&self, location: GLint, x: GLint) {
+        unsafe { gl::Uniform1i(location, x) }
+    }
+
+    fn uniform_matrix(&self, location: GLint, size: u32, values: &[GLfloat]) {
+        assert_eq!(values.len(), (size * size) as usize, "A {0}x{0} matrix needs {1} values", size, size * size);
+        unsafe {
+            match size {
+                2 => gl::UniformMatrix2fv(location, 1, gl::FALSE, values.as_ptr()),
+                3 => gl::UniformMatrix3fv(location, 1, gl::FALSE, values.as_ptr()),
+                4 => gl::UniformMatrix4fv(location, 1, gl::FALSE, values.as_ptr()),
+                _ => panic!("Unsupported matrix size {}", size)
+            }
+        }
+    }
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: GLsizei) {
+        unsafe { gl::DrawArrays(mode, first, count) }
+    }
+
+    fn draw_range_elements(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize) {
+        unsafe { gl::DrawRangeElements(mode, start, end, count, gltype, offset as *const GLvoid) }
+    }
+
+    fn draw_range_elements_base_vertex(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize, base_vertex: GLint) {
+        unsafe { gl::DrawRangeElementsBaseVertex(mode, start, end, count, gltype, offset as *const GLvoid, base_vertex) }
+    }
+
+    fn gen_query(&self) -> GLuint {
+        let mut id = 0;
+        unsafe { gl::GenQueries(1, &mut id) };
+        id
+    }
+
+    fn delete_query(&self, id: GLuint) {
+        unsafe { gl::DeleteQueries(1, &id) }
+    }
+
+    fn begin_query(&self, target: GLenum, id: GLuint) {
+        unsafe { gl::BeginQuery(target, id) }
+    }
+
+    fn end_query(&self, target: GLenum) {
+        unsafe { gl::EndQuery(target) }
+    }
+
+    fn query_counter(&self, id: GLuint) {
+        unsafe { gl::QueryCounter(id, gl::TIMESTAMP) }
+    }
+
+    fn get_query_result_available(&self, id: GLuint) -> bool {
+        let mut available = 0;
+        unsafe { gl::GetQueryObjectiv(id, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+        available != 0
+    }
+
+    fn get_query_result(&self, id: GLuint) -> u64 {
+        let mut result = 0;
+        unsafe { gl::GetQueryObjectui64v(id, gl::QUERY_RESULT, &mut result) };
+        result
+    }
+
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, user: *const c_void) {
+        gl::DebugMessageCallback(callback, user)
+    }
+
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str) {
+        unsafe { gl::ObjectLabel(identifier, id, -1, c_string(label).as_ptr()) }
+    }
+}
+
+fn info_log_to_string(mut log: Vec<u8>) -> String {
+    while log.last() == Some(&0) {
+        log.pop();
+    }
+    String::from_utf8_lossy(&log).into_owned()
+}
@@ -0,0 +1,121 @@
+use std::os::raw::c_void;
+
+use gl::types::*;
+
+mod gl_backend;
+pub use self::gl_backend::GlBackend;
+
+#[cfg(test)]
+mod recording;
+#[cfg(test)]
+pub use self::recording::{ RecordingBackend, Call, Arg };
+
+/// The OpenGL functions used by `gfx`. Every GL call made by a `Context` and the resources created
+/// from it goes through its backend, so rendering code can be run against a `RecordingBackend`
+/// in tests.
+///
+/// Pointers are replaced by slices and strings, and object names are returned rather than
+/// written through out parameters.
+pub trait Backend {
+    fn get_error(&self) -> GLenum;
+    fn get_integer(&self, pname: GLenum) -> GLint;
+    fn get_string(&self, name: GLenum) -> String;
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> String;
+
+    fn enable(&self, cap: GLenum);
+    fn disable(&self, cap: GLenum);
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+    fn scissor(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+
+    fn gen_buffer(&self) -> GLuint;
+    fn delete_buffer(&self, id: GLuint);
+    fn bind_buffer(&self, target: GLenum, id: GLuint);
+    /// Allocates `size` bytes, initialized from `data` if given.
+    fn buffer_data(&self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum);
+    fn buffer_sub_data(&self, target: GLenum, offset: usize, data: &[u8]);
+    fn copy_buffer_sub_data(&self, read_target: GLenum, write_target: GLenum, read_offset: usize, write_offset: usize, size: usize);
+
+    fn gen_vertex_array(&self) -> GLuint;
+    fn delete_vertex_array(&self, id: GLuint);
+    fn bind_vertex_array(&self, id: GLuint);
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, gltype: GLenum, normalized: bool, stride: GLsizei, offset: usize);
+    fn enable_vertex_attrib_array(&self, index: GLuint);
+
+    fn gen_texture(&self) -> GLuint;
+    fn delete_texture(&self, id: GLuint);
+    /// Selects texture unit `TEXTURE0 + unit`.
+    fn active_texture(&self, unit: GLuint);
+    fn bind_texture(&self, target: GLenum, id: GLuint);
+    /// `data` holds tightly packed rows.
+    fn tex_image_2d(&self, internal_format: GLenum, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, data: Option<&[u8]>);
+    fn tex_parameter_i(&self, pname: GLenum, value: GLint);
+
+    fn gen_framebuffer(&self) -> GLuint;
+    fn delete_framebuffer(&self, id: GLuint);
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint);
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture: GLuint);
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint);
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum;
+    fn draw_buffer(&self, buffer: GLenum);
+    fn draw_buffers(&self, buffers: &[GLenum]);
+    fn read_buffer(&self, buffer: GLenum);
+    /// Rectangles are `[x0, y0, x1, y1]`.
+    fn blit_framebuffer(&self, src: [GLint; 4], dst: [GLint; 4], mask: GLbitfield, filter: GLenum);
+    /// `value` holds 4 components when clearing color, and 1 otherwise.
+    fn clear_buffer_fv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLfloat]);
+    fn clear_buffer_iv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLint]);
+    fn clear_buffer_uiv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLuint]);
+    /// Reads tightly packed rows into `into`.
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, into: &mut [u8]);
+
+    fn gen_renderbuffer(&self) -> GLuint;
+    fn delete_renderbuffer(&self, id: GLuint);
+    fn bind_renderbuffer(&self, id: GLuint);
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei);
+    /// The sample count of the bound renderbuffer.
+    fn get_renderbuffer_samples(&self) -> GLint;
+
+    fn create_shader(&self, shader_type: GLenum) -> GLuint;
+    fn delete_shader(&self, id: GLuint);
+    fn shader_source(&self, id: GLuint, source: &str);
+    fn compile_shader(&self, id: GLuint);
+    fn get_compile_status(&self, id: GLuint) -> bool;
+    fn get_shader_info_log(&self, id: GLuint) -> String;
+    fn create_program(&self) -> GLuint;
+    fn delete_program(&self, id: GLuint);
+    fn attach_shader(&self, program: GLuint, shader: GLuint);
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str);
+    fn link_program(&self, id: GLuint);
+    fn get_link_status(&self, id: GLuint) -> bool;
+    fn get_program_info_log(&self, id: GLuint) -> String;
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint;
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint;
+    fn use_program(&self, id: GLuint);
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat);
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat);
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat);
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat);
+    fn uniform_1i(&self, location: GLint, x: GLint);
+    /// Sets a `size`x`size` matrix from column major `values`.
+    fn uniform_matrix(&self, location: GLint, size: u32, values: &[GLfloat]);
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: GLsizei);
+    /// Draws `count` indices starting `offset` bytes into the bound element array buffer. All
+    /// indices must lie within `start..=end`.
+    fn draw_range_elements(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize);
+    fn draw_range_elements_base_vertex(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize, base_vertex: GLint);
+
+    fn gen_query(&self) -> GLuint;
+    fn delete_query(&self, id: GLuint);
+    fn begin_query(&self, target: GLenum, id: GLuint);
+    fn end_query(&self, target: GLenum);
+    fn query_counter(&self, id: GLuint);
+    fn get_query_result_available(&self, id: GLuint) -> bool;
+    fn get_query_result(&self, id: GLuint) -> u64;
+
+    /// Only called if `KHR_debug` is available. `user` must stay valid until another callback is
+    /// registered.
+    unsafe fn debug_message_callback(&self, callback: GLDEBUGPROC, user: *const c_void);
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str);
+}
@@ -0,0 +1,495 @@
+use std::cell::RefCell;
+use std::collections::{ HashMap, HashSet };
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+use gl::types::*;
+use gl;
+
+use gfx::backend::Backend;
+
+/// An argument of a recorded call.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Arg {
+    Int(i64),
+    Float(f32),
+    Str(String),
+    Ints(Vec<i64>),
+    Floats(Vec<f32>),
+    /// Data passed to the GL, or `None` for a null pointer.
+    Data(Option<Vec<u8>>)
+}
+
+impl From<i32> for Arg {
+    fn from(v: i32) -> Arg { Arg::Int(v as i64) }
+}
+
+impl From<u32> for Arg {
+    fn from(v: u32) -> Arg { Arg::Int(v as i64) }
+}
+
+impl From<usize> for Arg {
+    fn from(v: usize) -> Arg { Arg::Int(v as i64) }
+}
+
+impl From<bool> for Arg {
+    fn from(v: bool) -> Arg { Arg::Int(v as i64) }
+}
+
+impl From<f32> for Arg {
+    fn from(v: f32) -> Arg { Arg::Float(v) }
+}
+
+impl<'s> From<&'s str> for Arg {
+    fn from(v: &'s str) -> Arg { Arg::Str(v.to_owned()) }
+}
+
+impl<'s> From<&'s [f32]> for Arg {
+    fn from(v: &'s [f32]) -> Arg { Arg::Floats(v.to_vec()) }
+}
+
+impl<'s> From<&'s [i32]> for Arg {
+    fn from(v: &'s [i32]) -> Arg { Arg::Ints(v.iter().map(|&i| i as i64).collect()) }
+}
+
+impl<'s> From<&'s [u32]> for Arg {
+    fn from(v: &'s [u32]) -> Arg { Arg::Ints(v.iter().map(|&i| i as i64).collect()) }
+}
+
+impl From<[i32; 4]> for Arg {
+    fn from(v: [i32; 4]) -> Arg { Arg::from(&v[..]) }
+}
+
+impl<'s> From<Option<&'s [u8]>> for Arg {
+    fn from(v: Option<&'s [u8]>) -> Arg { Arg::Data(v.map(|d| d.to_vec())) }
+}
+
+impl<'s> From<&'s [u8]> for Arg {
+    fn from(v: &'s [u8]) -> Arg { Arg::Data(Some(v.to_vec())) }
+}
+
+/// A GL call, named after the GL function without the `gl` prefix.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Call {
+    pub name: &'static str,
+    pub args: Vec<Arg>
+}
+
+#[derive(Default)]
+struct Recording {
+    calls: Vec<Call>,
+    next_id: GLuint,
+    live: HashSet<(&'static str, GLuint)>,
+    /// Attribute and uniform locations, keyed by the function that queried them.
+    locations: HashMap<(&'static str, GLuint, String), GLint>,
+    renderbuffer_samples: GLint
+}
+
+/// A backend that records every call instead of drawing anything. Object creation returns fresh
+/// names, shaders always compile, framebuffers are always complete and queries always have a
+/// result of 0.
+///
+/// Clones share the same recording, so keep one to inspect calls after handing another to
+/// `Context::with_backend`.
+#[derive(Clone, Default)]
+pub struct RecordingBackend {
+    recording: Rc<RefCell<Recording>>
+}
+
+macro_rules! record {
+    ($self_:ident, $name:expr $(, $arg:expr)*) => {
+        $self_.recording.borrow_mut().calls.push(Call {
+            name: $name,
+            args: vec![$(Arg::from($arg)),*]
+        })
+    };
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        RecordingBackend::default()
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.recording.borrow().calls.clone()
+    }
+
+    /// The calls to the GL function `name`, in order.
+    pub fn calls_to(&self, name: &str) -> Vec<Call> {
+        self.recording.borrow().calls.iter().filter(|c| c.name == name).cloned().collect()
+    }
+
+    pub fn clear_calls(&self) {
+        self.recording.borrow_mut().calls.clear();
+    }
+
+    /// The number of objects of a kind ("Buffer", "Texture", "Framebuffer", "Renderbuffer",
+    /// "VertexArray", "Shader", "Program" or "Query") that were created and not deleted.
+    pub fn live_objects(&self, kind: &str) -> usize {
+        self.recording.borrow().live.iter().filter(|&&(k, _)| k == kind).count()
+    }
+
+    fn create(&self, kind: &'static str, name: &'static str) -> GLuint {
+        let mut r = self.recording.borrow_mut();
+        r.next_id += 1;
+        let id = r.next_id;
+        r.live.insert((kind, id));
+        r.calls.push(Call { name: name, args: vec![Arg::from(id)] });
+        id
+    }
+
+    fn delete(&self, kind: &'static str, name: &'static str, id: GLuint) {
+        let mut r = self.recording.borrow_mut();
+        r.live.remove(&(kind, id));
+        r.calls.push(Call { name: name, args: vec![Arg::from(id)] });
+    }
+
+    fn location(&self, name: &'static str, program: GLuint, var: &str) -> GLint {
+        record!(self, name, program, var);
+        let mut r = self.recording.borrow_mut();
+        let next = r.locations.keys().filter(|&&(n, p, _)| n == name && p == program).count() as GLint;
+        *r.locations.entry((name, program, var.to_owned())).or_insert(next)
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn get_error(&self) -> GLenum {
+        gl::NO_ERROR
+    }
+
+    fn get_integer(&self, pname: GLenum) -> GLint {
+        record!(self, "GetIntegerv", pname);
+        match pname {
+            gl::MAJOR_VERSION => 3,
+            gl::MINOR_VERSION => 3,
+            gl::NUM_EXTENSIONS => 0,
+            gl::MAX_TEXTURE_SIZE | gl::MAX_RENDERBUFFER_SIZE => 16384,
+            gl::MAX_VERTEX_ATTRIBS | gl::MAX_TEXTURE_IMAGE_UNITS => 16,
+            gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS => 48,
+            gl::MAX_SAMPLES | gl::MAX_COLOR_ATTACHMENTS | gl::MAX_DRAW_BUFFERS => 8,
+            gl::MAX_VERTEX_UNIFORM_COMPONENTS | gl::MAX_FRAGMENT_UNIFORM_COMPONENTS => 1024,
+            _ => 0
+        }
+    }
+
+    fn get_string(&self, name: GLenum) -> String {
+        record!(self, "GetString", name);
+        match name {
+            gl::VERSION => "3.3 Recording".to_owned(),
+            gl::SHADING_LANGUAGE_VERSION => "3.30".to_owned(),
+            _ => "Recording".to_owned()
+        }
+    }
+
+    fn get_string_i(&self, name: GLenum, index: GLuint) -> String {
+        record!(self, "GetStringi", name, index);
+        String::new()
+    }
+
+    fn enable(&self, cap: GLenum) {
+        record!(self, "Enable", cap);
+    }
+
+    fn disable(&self, cap: GLenum) {
+        record!(self, "Disable", cap);
+    }
+
+    fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        record!(self, "Viewport", x, y, width, height);
+    }
+
+    fn scissor(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+        record!(self, "Scissor", x, y, width, height);
+    }
+
+    fn gen_buffer(&self) -> GLuint {
+        self.create("Buffer", "GenBuffers")
+    }
+
+    fn delete_buffer(&self, id: GLuint) {
+        self.delete("Buffer", "DeleteBuffers", id);
+    }
+
+    fn bind_buffer(&self, target: GLenum, id: GLuint) {
+        record!(self, "BindBuffer", target, id);
+    }
+
+    fn buffer_data(&self, target: GLenum, size: usize, data: Option<&[u8]>, usage: GLenum) {
+        record!(self, "BufferData", target, size, data, usage);
+    }
+
+    fn buffer_sub_data(&self, target: GLenum, offset: usize, data: &[u8]) {
+        record!(self, "BufferSubData", target, offset, data);
+    }
+
+    fn copy_buffer_sub_data(&self, read_target: GLenum, write_target: GLenum, read_offset: usize, write_offset: usize, size: usize) {
+        record!(self, "CopyBufferSubData", read_target, write_target, read_offset, write_offset, size);
+    }
+
+    fn gen_vertex_array(&self) -> GLuint {
+        self.create("VertexArray", "GenVertexArrays")
+    }
+
+    fn delete_vertex_array(&self, id: GLuint) {
+        self.delete("VertexArray", "DeleteVertexArrays", id);
+    }
+
+    fn bind_vertex_array(&self, id: GLuint) {
+        record!(self, "BindVertexArray", id);
+    }
+
+    fn vertex_attrib_pointer(&self, index: GLuint, size: GLint, gltype: GLenum, normalized: bool, stride: GLsizei, offset: usize) {
+        record!(self, "VertexAttribPointer", index, size, gltype, normalized, stride, offset);
+    }
+
+    fn enable_vertex_attrib_array(&self, index: GLuint) {
+        record!(self, "EnableVertexAttribArray", index);
+    }
+
+    fn gen_texture(&self) -> GLuint {
+        self.create("Texture", "GenTextures")
+    }
+
+    fn delete_texture(&self, id: GLuint) {
+        self.delete("Texture", "DeleteTextures", id);
+    }
+
+    fn active_texture(&self, unit: GLuint) {
+        record!(self, "ActiveTexture", unit);
+    }
+
+    fn bind_texture(&self, target: GLenum, id: GLuint) {
+        record!(self, "BindTexture", target, id);
+    }
+
+    fn tex_image_2d(&self, internal_format: GLenum, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, data: Option<&[u8]>) {
+        record!(self, "TexImage2D", internal_format, width, height, format, gltype, data);
+    }
+
+    fn tex_parameter_i(&self, pname: GLenum, value: GLint) {
+        record!(self, "TexParameteri", pname, value);
+    }
+
+    fn gen_framebuffer(&self) -> GLuint {
+        self.create("Framebuffer", "GenFramebuffers")
+    }
+
+    fn delete_framebuffer(&self, id: GLuint) {
+        self.delete("Framebuffer", "DeleteFramebuffers", id);
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, id: GLuint) {
+        record!(self, "BindFramebuffer", target, id);
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture: GLuint) {
+        record!(self, "FramebufferTexture2D", target, attachment, texture);
+    }
+
+    fn framebuffer_renderbuffer(&self, target: GLenum, attachment: GLenum, renderbuffer: GLuint) {
+        record!(self, "FramebufferRenderbuffer", target, attachment, renderbuffer);
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        record!(self, "CheckFramebufferStatus", target);
+        gl::FRAMEBUFFER_COMPLETE
+    }
+
+    fn draw_buffer(&self, buffer: GLenum) {
+        record!(self, "DrawBuffer", buffer);
+    }
+
+    fn draw_buffers(&self, buffers: &[GLenum]) {
+        record!(self, "DrawBuffers", buffers);
+    }
+
+    fn read_buffer(&self, buffer: GLenum) {
+        record!(self, "ReadBuffer", buffer);
+    }
+
+    fn blit_framebuffer(&self, src: [GLint; 4], dst: [GLint; 4], mask: GLbitfield, filter: GLenum) {
+        record!(self, "BlitFramebuffer", src, dst, mask, filter);
+    }
+
+    fn clear_buffer_fv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLfloat]) {
+        record!(self, "ClearBufferfv", buffer, draw_buffer, value);
+    }
+
+    fn clear_buffer_iv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLint]) {
+        record!(self, "ClearBufferiv", buffer, draw_buffer, value);
+    }
+
+    fn clear_buffer_uiv(&self, buffer: GLenum, draw_buffer: GLint, value: &[GLuint]) {
+        record!(self, "ClearBufferuiv", buffer, draw_buffer, value);
+    }
+
+    fn read_pixels(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, gltype: GLenum, into: &mut [u8]) {
+        record!(self, "ReadPixels", x, y, width, height, format, gltype);
+        for b in into {
+            *b = 0;
+        }
+    }
+
+    fn gen_renderbuffer(&self) -> GLuint {
+        self.create("Renderbuffer", "GenRenderbuffers")
+    }
+
+    fn delete_renderbuffer(&self, id: GLuint) {
+        self.delete("Renderbuffer", "DeleteRenderbuffers", id);
+    }
+
+    fn bind_renderbuffer(&self, id: GLuint) {
+        record!(self, "BindRenderbuffer", id);
+    }
+
+    fn renderbuffer_storage_multisample(&self, samples: GLsizei, format: GLenum, width: GLsizei, height: GLsizei) {
+        record!(self, "RenderbufferStorageMultisample", samples, format, width, height);
+        self.recording.borrow_mut().renderbuffer_samples = samples;
+    }
+
+    fn get_renderbuffer_samples(&self) -> GLint {
+        record!(self, "GetRenderbufferParameteriv");
+        self.recording.borrow().renderbuffer_samples
+    }
+
+    fn create_shader(&self, shader_type: GLenum) -> GLuint {
+        let id = self.create("Shader", "CreateShader");
+        self.recording.borrow_mut().calls.last_mut().unwrap().args.push(Arg::from(shader_type));
+        id
+    }
+
+    fn delete_shader(&self, id: GLuint) {
+        self.delete("Shader", "DeleteShader", id);
+    }
+
+    fn shader_source(&self, id: GLuint, source: &str) {
+        record!(self, "ShaderSource", id, source);
+    }
+
+    fn compile_shader(&self, id: GLuint) {
+        record!(self, "CompileShader", id);
+    }
+
+    fn get_compile_status(&self, _id: GLuint) -> bool {
+        true
+    }
+
+    fn get_shader_info_log(&self, _id: GLuint) -> String {
+        String::new()
+    }
+
+    fn create_program(&self) -> GLuint {
+        self.create("Program", "CreateProgram")
+    }
+
+    fn delete_program(&self, id: GLuint) {
+        self.delete("Program", "DeleteProgram", id);
+    }
+
+    fn attach_shader(&self, program: GLuint, shader: GLuint) {
+        record!(self, "AttachShader", program, shader);
+    }
+
+    fn bind_frag_data_location(&self, program: GLuint, color: GLuint, name: &str) {
+        record!(self, "BindFragDataLocation", program, color, name);
+    }
+
+    fn link_program(&self, id: GLuint) {
+        record!(self, "LinkProgram", id);
+    }
+
+    fn get_link_status(&self, _id: GLuint) -> bool {
+        true
+    }
+
+    fn get_program_info_log(&self, _id: GLuint) -> String {
+        String::new()
+    }
+
+    fn get_attrib_location(&self, program: GLuint, name: &str) -> GLint {
+        self.location("GetAttribLocation", program, name)
+    }
+
+    fn get_uniform_location(&self, program: GLuint, name: &str) -> GLint {
+        self.location("GetUniformLocation", program, name)
+    }
+
+    fn use_program(&self, id: GLuint) {
+        record!(self, "UseProgram", id);
+    }
+
+    fn uniform_1f(&self, location: GLint, x: GLfloat) {
+        record!(self, "Uniform1f", location, x);
+    }
+
+    fn uniform_2f(&self, location: GLint, x: GLfloat, y: GLfloat) {
+        record!(self, "Uniform2f", location, x, y);
+    }
+
+    fn uniform_3f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat) {
+        record!(self, "Uniform3f", location, x, y, z);
+    }
+
+    fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+        record!(self, "Uniform4f", location, x, y, z, w);
+    }
+
+    fn uniform_1i(&self, location: GLint, x: GLint) {
+        record!(self, "Uniform1i", location, x);
+    }
+
+    fn uniform_matrix(&self, location: GLint, size: u32, values: &[GLfloat]) {
+        let name = match size {
+            2 => "UniformMatrix2fv",
+            3 => "UniformMatrix3fv",
+            _ => "UniformMatrix4fv"
+        };
+        record!(self, name, location, values);
+    }
+
+    fn draw_arrays(&self, mode: GLenum, first: GLint, count: GLsizei) {
+        record!(self, "DrawArrays", mode, first, count);
+    }
+
+    fn draw_range_elements(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize) {
+        record!(self, "DrawRangeElements", mode, start, end, count, gltype, offset);
+    }
+
+    fn draw_range_elements_base_vertex(&self, mode: GLenum, start: GLuint, end: GLuint, count: GLsizei, gltype: GLenum, offset: usize, base_vertex: GLint) {
+        record!(self, "DrawRangeElementsBaseVertex", mode, start, end, count, gltype, offset, base_vertex);
+    }
+
+    fn gen_query(&self) -> GLuint {
+        self.create("Query", "GenQueries")
+    }
+
+    fn delete_query(&self, id: GLuint) {
+        self.delete("Query", "DeleteQueries", id);
+    }
+
+    fn begin_query(&self, target: GLenum, id: GLuint) {
+        record!(self, "BeginQuery", target, id);
+    }
+
+    fn end_query(&self, target: GLenum) {
+        record!(self, "EndQuery", target);
+    }
+
+    fn query_counter(&self, id: GLuint) {
+        record!(self, "QueryCounter", id);
+    }
+
+    fn get_query_result_available(&self, _id: GLuint) -> bool {
+        true
+    }
+
+    fn get_query_result(&self, _id: GLuint) -> u64 {
+        0
+    }
+
+    unsafe fn debug_message_callback(&self, _callback: GLDEBUGPROC, _user: *const c_void) {}
+
+    fn object_label(&self, identifier: GLenum, id: GLuint, label: &str) {
+        record!(self, "ObjectLabel", identifier, id, label);
+    }
+}
@@ -1,27 +1,60 @@
 use std::marker::PhantomData;
 use std::mem::size_of;
-use std::os::raw::c_void;
+use std::ops::Range;
+use std::slice;
 
 use gl::types::*;
 use gl;
 
-use gfx::{ Context };
+use gfx::{ Context, ResourceKind };
 use gfx::vertex;
 
+/// How often the contents of a buffer are expected to change. This is only a hint to the driver.
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BufferUsage {
+    /// Written once and drawn many times.
+    Static = gl::STATIC_DRAW,
+    /// Rewritten occasionally and drawn many times.
+    Dynamic = gl::DYNAMIC_DRAW,
+    /// Rewritten about every time it is drawn, like per-frame sprite or particle data.
+    Stream = gl::STREAM_DRAW
+}
+
+/// A buffer of vertices. Its length is the number of vertices drawn from it, which may be less
+/// than the number of vertices its storage has room for.
 pub struct VertexBuffer<'a, S: vertex::Struct> {
     pub (crate) id: GLuint,
     pub (crate) elems: usize,
+    capacity: usize,
+    usage: BufferUsage,
     ctx: &'a Context,
     _phantom: PhantomData<S>,
 }
 
+pub(crate) fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * size_of::<T>()) }
+}
+
+impl<'a, S: vertex::Struct> Drop for VertexBuffer<'a, S> {
+    fn drop(&mut self) {
+        self.ctx.delete_buffer(self.id);
+    }
+}
+
 impl Context {
     pub fn create_vertex_buffer<'a, S: vertex::Struct>(&'a self) -> VertexBuffer<'a, S> {
-        let mut id = 0;
-        unsafe { gl::GenBuffers(1, &mut id) };
+        self.create_vertex_buffer_with_usage(BufferUsage::Static)
+    }
+
+    pub fn create_vertex_buffer_with_usage<'a, S: vertex::Struct>(&'a self, usage: BufferUsage) -> VertexBuffer<'a, S> {
+        let id = self.gl.gen_buffer();
+        self.track(ResourceKind::Buffer, id);
         VertexBuffer {
             id: id,
             elems: 0,
+            capacity: 0,
+            usage: usage,
             ctx: self,
             _phantom: PhantomData,
         }
@@ -29,25 +62,344 @@ impl Context {
 }
 
 impl<'a, S: vertex::Struct> VertexBuffer<'a, S> {
+    /// Replaces the storage with exactly `data`.
     pub fn allocate(&mut self, data: &[S]) {
         self.bind();
+        self.ctx.gl.buffer_data(gl::ARRAY_BUFFER, data.len() * size_of::<S>(), Some(as_bytes(data)), self.usage as GLenum);
         self.elems = data.len();
-        unsafe {
-            gl::BufferData(gl::ARRAY_BUFFER, (data.len() * size_of::<S>()) as isize, data.as_ptr() as *const c_void, gl::STATIC_DRAW);
+        self.set_capacity(data.len());
+        self.ctx.check_errors("VertexBuffer::allocate");
+    }
+
+    /// Replaces the contents with `data`, orphaning the old storage so the driver doesn't have to
+    /// wait for draws still reading from it. Only grows the storage if `data` doesn't fit.
+    pub fn write(&mut self, data: &[S]) {
+        let capacity = if data.len() > self.capacity { grown_capacity(self.capacity, data.len()) } else { self.capacity };
+        self.bind();
+        self.ctx.gl.buffer_data(gl::ARRAY_BUFFER, capacity * size_of::<S>(), None, self.usage as GLenum);
+        self.ctx.gl.buffer_sub_data(gl::ARRAY_BUFFER, 0, as_bytes(data));
+        self.elems = data.len();
+        self.set_capacity(capacity);
+        self.ctx.check_errors("VertexBuffer::write");
+    }
+
+    /// Appends `data`, growing the storage if needed. Existing vertices are kept.
+    pub fn extend(&mut self, data: &[S]) {
+        let len = self.elems + data.len();
+        if len > self.capacity {
+            let capacity = grown_capacity(self.capacity, len);
+            self.reserve(capacity);
         }
+        self.bind();
+        self.ctx.gl.buffer_sub_data(gl::ARRAY_BUFFER, self.elems * size_of::<S>(), as_bytes(data));
+        self.elems = len;
+        self.ctx.check_errors("VertexBuffer::extend");
     }
 
+    /// Overwrites the vertices starting at vertex `start`, which must already be within the length
+    /// of the buffer.
     pub fn set_slice(&mut self, start: usize, data: &[S]) {
+        assert!(
+            start.checked_add(data.len()).map_or(false, |end| end <= self.elems),
+            "Vertices {}..{} are out of bounds for a vertex buffer of length {}", start, start.saturating_add(data.len()), self.elems
+        );
         self.bind();
-        if start + data.len() > self.elems {
-            panic!("out of bounds: {}", start + data.len());
+        self.ctx.gl.buffer_sub_data(gl::ARRAY_BUFFER, start * size_of::<S>(), as_bytes(data));
+        self.ctx.check_errors("VertexBuffer::set_slice");
+    }
+
+    /// Makes room for at least `capacity` vertices, keeping the current contents.
+    pub fn reserve(&mut self, capacity: usize) {
+        if capacity <= self.capacity {
+            return;
         }
-        unsafe {
-            gl::BufferSubData(gl::ARRAY_BUFFER, start as isize, (data.len() * size_of::<S>()) as isize, data.as_ptr() as *const c_void);
+        let bytes = self.elems * size_of::<S>();
+        let usage = self.usage as GLenum;
+        self.bind();
+        if bytes == 0 {
+            self.ctx.gl.buffer_data(gl::ARRAY_BUFFER, capacity * size_of::<S>(), None, usage);
+        } else {
+            // Reallocating discards the contents, so park them in a temporary buffer. Keeping
+            // the same buffer name means cached vertex arrays stay valid.
+            let temp = self.ctx.gl.gen_buffer();
+            self.ctx.bind_copy_write_buffer(temp);
+            self.ctx.gl.buffer_data(gl::COPY_WRITE_BUFFER, bytes, None, gl::STREAM_COPY);
+            self.ctx.gl.copy_buffer_sub_data(gl::ARRAY_BUFFER, gl::COPY_WRITE_BUFFER, 0, 0, bytes);
+            self.ctx.gl.buffer_data(gl::ARRAY_BUFFER, capacity * size_of::<S>(), None, usage);
+            self.ctx.gl.copy_buffer_sub_data(gl::COPY_WRITE_BUFFER, gl::ARRAY_BUFFER, 0, 0, bytes);
+            self.ctx.delete_buffer(temp);
         }
+        self.set_capacity(capacity);
+        self.ctx.check_errors("VertexBuffer::reserve");
+    }
+
+    /// Discards the contents and sets the length to 0, keeping the capacity.
+    pub fn clear(&mut self) {
+        self.bind();
+        self.ctx.gl.buffer_data(gl::ARRAY_BUFFER, self.capacity * size_of::<S>(), None, self.usage as GLenum);
+        self.elems = 0;
+        self.ctx.check_errors("VertexBuffer::clear");
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elems == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get_usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// Changes the usage hint. Takes effect the next time the storage is reallocated.
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        self.bind();
+        self.ctx.label_object(gl::BUFFER, self.id, label);
     }
 
     pub (crate) fn bind(&mut self) {
         self.ctx.bind_array_buffer(self.id);
     }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.ctx.set_tracked_size(ResourceKind::Buffer, self.id, capacity * size_of::<S>());
+    }
+}
+
+/// At least doubles `capacity`, so repeated appends only reallocate a logarithmic number of times.
+fn grown_capacity(capacity: usize, needed: usize) -> usize {
+    needed.max(capacity * 2)
+}
+
+/// A type that can be stored in an `IndexBuffer`.
+pub trait Index : Copy {
+    const GL_TYPE: GLenum;
+
+    fn to_u32(self) -> u32;
+}
+
+impl Index for u8 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_BYTE;
+
+    fn to_u32(self) -> u32 { self as u32 }
+}
+
+impl Index for u16 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_SHORT;
+
+    fn to_u32(self) -> u32 { self as u32 }
+}
+
+impl Index for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+
+    fn to_u32(self) -> u32 { self }
+}
+
+pub struct IndexBuffer<'a, I: Index> {
+    pub (crate) id: GLuint,
+    pub (crate) elems: usize,
+    /// A copy of the contents, so draws can find the vertices used by the indices they draw.
+    indices: Vec<u32>,
+    ctx: &'a Context,
+    _phantom: PhantomData<I>,
+}
+
+impl<'a, I: Index> Drop for IndexBuffer<'a, I> {
+    fn drop(&mut self) {
+        self.ctx.delete_buffer(self.id);
+    }
+}
+
+impl Context {
+    pub fn create_index_buffer<'a, I: Index>(&'a self) -> IndexBuffer<'a, I> {
+        let id = self.gl.gen_buffer();
+        self.track(ResourceKind::Buffer, id);
+        IndexBuffer {
+            id: id,
+            elems: 0,
+            indices: Vec::new(),
+            ctx: self,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, I: Index> IndexBuffer<'a, I> {
+    pub fn allocate(&mut self, data: &[I]) {
+        self.bind();
+        self.elems = data.len();
+        self.indices = data.iter().map(|i| i.to_u32()).collect();
+        self.ctx.gl.buffer_data(gl::COPY_WRITE_BUFFER, data.len() * size_of::<I>(), Some(as_bytes(data)), gl::STATIC_DRAW);
+        self.ctx.set_tracked_size(ResourceKind::Buffer, self.id, data.len() * size_of::<I>());
+        self.ctx.check_errors("IndexBuffer::allocate");
+    }
+
+    /// Overwrites the indices starting at index `start`.
+    pub fn set_slice(&mut self, start: usize, data: &[I]) {
+        assert!(
+            start.checked_add(data.len()).map_or(false, |end| end <= self.elems),
+            "Indices {}..{} are out of bounds for an index buffer of length {}", start, start.saturating_add(data.len()), self.elems
+        );
+        self.bind();
+        for (dst, src) in self.indices[start..].iter_mut().zip(data) {
+            *dst = src.to_u32();
+        }
+        self.ctx.gl.buffer_sub_data(gl::COPY_WRITE_BUFFER, start * size_of::<I>(), as_bytes(data));
+        self.ctx.check_errors("IndexBuffer::set_slice");
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        self.bind();
+        self.ctx.label_object(gl::BUFFER, self.id, label);
+    }
+
+    /// The smallest and largest of the indices in `range`, passed to `glDrawRangeElements`, or
+    /// `(0, 0)` if the range is empty.
+    pub(crate) fn bounds(&self, range: Range<usize>) -> (u32, u32) {
+        let mut indices = self.indices[range].iter();
+        match indices.next() {
+            Some(&first) => indices.fold((first, first), |(lo, hi), &i| (lo.min(i), hi.max(i))),
+            None => (0, 0)
+        }
+    }
+
+    fn bind(&mut self) {
+        self.ctx.bind_copy_write_buffer(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec2;
+
+    use { vertex_attributes, vertex_struct_type, vertex_struct };
+    use gfx::{ Context, BufferUsage, glsl_type };
+    use gfx::backend::{ RecordingBackend, Call, Arg };
+    use gfx::vertex::format;
+    use super::as_bytes;
+
+    vertex_attributes!(Pos: glsl_type::Vec2);
+
+    type Vertex = vertex_struct_type!(Pos: format::FloatVec2);
+
+    fn vertices(range: ::std::ops::Range<usize>) -> Vec<Vertex> {
+        range.map(|i| vertex_struct!(Pos: vec2(i as f32, 0.0))).collect()
+    }
+
+    fn call(name: &'static str, args: Vec<Arg>) -> Call {
+        Call { name: name, args: args }
+    }
+
+    #[test]
+    fn set_slice_writes_at_the_byte_offset() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(0..4));
+        let mut indices = ctx.create_index_buffer::<u16>();
+        indices.allocate(&[0, 1, 2, 3]);
+        rec.clear_calls();
+
+        let data = vertices(10..12);
+        buf.set_slice(2, &data);
+        indices.set_slice(3, &[7]);
+        assert_eq!(rec.calls_to("BufferSubData"), vec![
+            call("BufferSubData", vec![Arg::from(gl::ARRAY_BUFFER), Arg::Int(16), Arg::from(as_bytes(&data))]),
+            call("BufferSubData", vec![Arg::from(gl::COPY_WRITE_BUFFER), Arg::Int(6), Arg::from(as_bytes(&[7u16]))])
+        ]);
+        assert_eq!(indices.bounds(0..4), (0, 7));
+    }
+
+    #[test]
+    fn index_bounds_cover_only_the_requested_range_of_the_current_contents() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut indices = ctx.create_index_buffer::<u32>();
+        indices.allocate(&[0, 1, 2, 100, 101, 102]);
+        assert_eq!(indices.bounds(0..6), (0, 102));
+        assert_eq!(indices.bounds(3..6), (100, 102));
+        assert_eq!(indices.bounds(2..2), (0, 0));
+
+        indices.set_slice(3, &[3, 4, 5]);
+        assert_eq!(indices.bounds(0..6), (0, 5), "Overwritten indices should no longer count");
+        indices.allocate(&[7, 8]);
+        assert_eq!(indices.bounds(0..2), (7, 8));
+    }
+
+    #[test]
+    #[should_panic(expected = "Vertices 3..5 are out of bounds for a vertex buffer of length 4")]
+    fn set_slice_past_the_end_panics() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(0..4));
+        buf.set_slice(3, &vertices(0..2));
+    }
+
+    #[test]
+    #[should_panic(expected = "are out of bounds for an index buffer of length 4")]
+    fn set_slice_with_an_overflowing_end_panics() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut indices = ctx.create_index_buffer::<u16>();
+        indices.allocate(&[0, 1, 2, 3]);
+        indices.set_slice(usize::max_value(), &[0, 1]);
+    }
+
+    #[test]
+    fn extend_past_the_capacity_copies_the_contents_into_the_same_buffer() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(0..2));
+        let id = buf.id;
+        rec.clear_calls();
+
+        let data = vertices(2..3);
+        buf.extend(&data);
+        let temp = match rec.calls_to("GenBuffers")[0].args[0] {
+            Arg::Int(temp) => temp as u32,
+            ref other => panic!("Unexpected buffer name {:?}", other)
+        };
+        let (array, copy) = (gl::ARRAY_BUFFER, gl::COPY_WRITE_BUFFER);
+        assert_eq!(rec.calls(), vec![
+            call("GenBuffers", vec![Arg::from(temp)]),
+            call("BindBuffer", vec![Arg::from(copy), Arg::from(temp)]),
+            call("BufferData", vec![Arg::from(copy), Arg::Int(16), Arg::Data(None), Arg::from(gl::STREAM_COPY)]),
+            call("CopyBufferSubData", vec![Arg::from(array), Arg::from(copy), Arg::Int(0), Arg::Int(0), Arg::Int(16)]),
+            call("BufferData", vec![Arg::from(array), Arg::Int(32), Arg::Data(None), Arg::from(gl::STATIC_DRAW)]),
+            call("CopyBufferSubData", vec![Arg::from(copy), Arg::from(array), Arg::Int(0), Arg::Int(0), Arg::Int(16)]),
+            call("DeleteBuffers", vec![Arg::from(temp)]),
+            call("BufferSubData", vec![Arg::from(array), Arg::Int(16), Arg::from(as_bytes(&data))])
+        ]);
+        assert_eq!(buf.id, id);
+        assert_eq!((buf.len(), buf.capacity()), (3, 4));
+        assert_eq!(rec.live_objects("Buffer"), 1);
+    }
+
+    #[test]
+    fn write_orphans_the_storage() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let mut buf = ctx.create_vertex_buffer_with_usage::<Vertex>(BufferUsage::Stream);
+        buf.allocate(&vertices(0..2));
+        rec.clear_calls();
+
+        let data = vertices(5..6);
+        buf.write(&data);
+        assert_eq!(rec.calls(), vec![
+            call("BufferData", vec![Arg::from(gl::ARRAY_BUFFER), Arg::Int(16), Arg::Data(None), Arg::from(gl::STREAM_DRAW)]),
+            call("BufferSubData", vec![Arg::from(gl::ARRAY_BUFFER), Arg::Int(0), Arg::from(as_bytes(&data))])
+        ]);
+        assert_eq!((buf.len(), buf.capacity()), (1, 2));
+    }
 }
@@ -0,0 +1,65 @@
+use gl;
+
+use gfx::Context;
+use gfx::backend::Backend;
+
+/// Information about the OpenGL implementation, queried once when the `Context` is created.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    pub version: (u32, u32),
+    pub version_string: String,
+    pub glsl_version: String,
+    pub vendor: String,
+    pub renderer: String,
+    pub extensions: Vec<String>,
+    pub max_texture_size: u32,
+    pub max_renderbuffer_size: u32,
+    pub max_vertex_attributes: u32,
+    /// Texture units available to a fragment shader, which is where all samplers are bound.
+    pub max_texture_units: u32,
+    pub max_combined_texture_units: u32,
+    pub max_samples: u32,
+    pub max_color_attachments: u32,
+    pub max_draw_buffers: u32,
+    pub max_vertex_uniform_components: u32,
+    pub max_fragment_uniform_components: u32
+}
+
+impl Capabilities {
+    pub(crate) fn query(gl: &Backend) -> Self {
+        let get = |name| gl.get_integer(name).max(0) as u32;
+        Capabilities {
+            version: (get(gl::MAJOR_VERSION), get(gl::MINOR_VERSION)),
+            version_string: gl.get_string(gl::VERSION),
+            glsl_version: gl.get_string(gl::SHADING_LANGUAGE_VERSION),
+            vendor: gl.get_string(gl::VENDOR),
+            renderer: gl.get_string(gl::RENDERER),
+            extensions: (0..get(gl::NUM_EXTENSIONS)).map(|i| gl.get_string_i(gl::EXTENSIONS, i)).collect(),
+            max_texture_size: get(gl::MAX_TEXTURE_SIZE),
+            max_renderbuffer_size: get(gl::MAX_RENDERBUFFER_SIZE),
+            max_vertex_attributes: get(gl::MAX_VERTEX_ATTRIBS),
+            max_texture_units: get(gl::MAX_TEXTURE_IMAGE_UNITS),
+            max_combined_texture_units: get(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_samples: get(gl::MAX_SAMPLES),
+            max_color_attachments: get(gl::MAX_COLOR_ATTACHMENTS),
+            max_draw_buffers: get(gl::MAX_DRAW_BUFFERS),
+            max_vertex_uniform_components: get(gl::MAX_VERTEX_UNIFORM_COMPONENTS),
+            max_fragment_uniform_components: get(gl::MAX_FRAGMENT_UNIFORM_COMPONENTS)
+        }
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|e| e == name)
+    }
+
+    /// Returns whether the context is at least version `major.minor`.
+    pub fn supports_version(&self, major: u32, minor: u32) -> bool {
+        self.version >= (major, minor)
+    }
+}
+
+impl Context {
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+}
@@ -0,0 +1,411 @@
+use std::cell::{ Cell, RefCell };
+use std::fmt::Write as FmtWrite;
+use std::fs::{ self, File };
+use std::io::{ self, Write };
+use std::path::Path;
+
+use gl::types::*;
+use image::RgbaImage;
+
+use gfx::{ Context, ResourceKind };
+
+/// Everything drawn with `RenderCommand::execute` during one frame.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FrameCapture {
+    pub draws: Vec<DrawCall>
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DrawCall {
+    pub program: ObjectName,
+    pub attributes: Vec<CapturedAttribute>,
+    pub uniforms: Vec<CapturedUniform>,
+    pub vertex_count: i32,
+    /// Set for indexed draws, in which case `vertex_count` is the number of indices drawn.
+    pub indices: Option<CapturedIndices>,
+    pub target: CapturedTarget,
+    /// The target surface right after the draw, if thumbnails were requested. Multisampled
+    /// targets and targets without a readable color attachment get no thumbnail.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub thumbnail: Option<RgbaImage>
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ObjectName {
+    pub id: u32,
+    pub label: Option<String>
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CapturedAttribute {
+    pub location: u32,
+    pub buffer: ObjectName,
+    pub components: i32,
+    pub gltype: u32,
+    pub normalized: bool,
+    pub stride: i32,
+    pub offset: usize
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CapturedIndices {
+    pub buffer: ObjectName,
+    pub gltype: u32,
+    pub first: usize,
+    pub base_vertex: i32
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CapturedUniform {
+    pub location: i32,
+    pub value: CapturedValue
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum CapturedValue {
+    /// Vectors and matrices are flattened, matrices in column major order.
+    Floats(Vec<f32>),
+    Sampler { unit: u32, texture: ObjectName }
+}
+
+/// The surface a draw went to. All rectangles are `(x, y, width, height)` in framebuffer
+/// coordinates.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CapturedTarget {
+    pub framebuffer: ObjectName,
+    pub rect: (u32, u32, u32, u32),
+    pub viewport: (i32, i32, u32, u32),
+    pub clip: (u32, u32, u32, u32)
+}
+
+pub(crate) struct CaptureState {
+    requested: Cell<Option<bool>>,
+    recording: RefCell<Option<FrameCapture>>,
+    thumbnails: Cell<bool>,
+    finished: RefCell<Option<FrameCapture>>
+}
+
+impl CaptureState {
+    pub fn new() -> Self {
+        CaptureState {
+            requested: Cell::new(None),
+            recording: RefCell::new(None),
+            thumbnails: Cell::new(false),
+            finished: RefCell::new(None)
+        }
+    }
+}
+
+impl Context {
+    /// Records every draw of the next frame. Collect the result with `take_capture` once the frame
+    /// has finished. Thumbnails read back the target after every draw, which is slow.
+    pub fn capture_next_frame(&self, thumbnails: bool) {
+        self.capture.requested.set(Some(thumbnails));
+    }
+
+    pub fn take_capture(&self) -> Option<FrameCapture> {
+        self.capture.finished.borrow_mut().take()
+    }
+
+    pub(crate) fn begin_frame(&self) {
+        if let Some(thumbnails) = self.capture.requested.take() {
+            self.capture.thumbnails.set(thumbnails);
+            *self.capture.recording.borrow_mut() = Some(FrameCapture::default());
+        }
+    }
+
+    pub(crate) fn end_frame(&self) {
+        if let Some(capture) = self.capture.recording.borrow_mut().take() {
+            *self.capture.finished.borrow_mut() = Some(capture);
+        }
+    }
+
+    pub(crate) fn is_capturing(&self) -> bool {
+        self.capture.recording.borrow().is_some()
+    }
+
+    pub(crate) fn capture_thumbnails(&self) -> bool {
+        self.capture.thumbnails.get()
+    }
+
+    pub(crate) fn record_draw(&self, draw: DrawCall) {
+        if let Some(ref mut capture) = *self.capture.recording.borrow_mut() {
+            capture.draws.push(draw);
+        }
+    }
+
+    pub(crate) fn object_name(&self, kind: ResourceKind, id: GLuint) -> ObjectName {
+        ObjectName {
+            id: id,
+            label: self.tracked_label(kind, id)
+        }
+    }
+}
+
+impl FrameCapture {
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        s.push_str("{\"draws\":[");
+        for (i, draw) in self.draws.iter().enumerate() {
+            if i != 0 {
+                s.push(',');
+            }
+            draw.write_json(&mut s, i);
+        }
+        s.push_str("]}");
+        s
+    }
+
+    /// Writes `frame.json`, an `index.html` overview and a PNG per thumbnail into `dir`.
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        File::create(dir.join("frame.json"))?.write_all(self.to_json().as_bytes())?;
+        for (i, draw) in self.draws.iter().enumerate() {
+            if let Some(ref thumbnail) = draw.thumbnail {
+                thumbnail.save(dir.join(format!("draw_{}.png", i)))
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            }
+        }
+        File::create(dir.join("index.html"))?.write_all(self.to_html().as_bytes())
+    }
+
+    fn to_html(&self) -> String {
+        let mut s = String::new();
+        s.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Frame capture</title>");
+        s.push_str("<style>body{font-family:monospace}table{border-collapse:collapse}");
+        s.push_str("td,th{border:1px solid #999;padding:4px;vertical-align:top}img{max-width:256px}</style>");
+        s.push_str("</head><body>\n<table>\n<tr><th>#</th><th>Program</th><th>Target</th><th>Vertices</th>");
+        s.push_str("<th>Attributes</th><th>Uniforms</th><th>Result</th></tr>\n");
+        for (i, draw) in self.draws.iter().enumerate() {
+            let t = &draw.target;
+            let _ = write!(
+                s, "<tr><td>{}</td><td>{}</td><td>{}<br>rect {:?}<br>viewport {:?}<br>clip {:?}</td><td>{}",
+                i, html_escape(&draw.program.to_string()), html_escape(&t.framebuffer.to_string()),
+                t.rect, t.viewport, t.clip, draw.vertex_count
+            );
+            if let Some(ref idx) = draw.indices {
+                let _ = write!(
+                    s, "<br>indices {} type 0x{:X} first {} base vertex {}",
+                    html_escape(&idx.buffer.to_string()), idx.gltype, idx.first, idx.base_vertex
+                );
+            }
+            s.push_str("</td><td>");
+            for a in &draw.attributes {
+                let _ = write!(
+                    s, "{}: {} x{} type 0x{:X}{} stride {} offset {}<br>",
+                    a.location, html_escape(&a.buffer.to_string()), a.components, a.gltype,
+                    if a.normalized { " normalized" } else { "" }, a.stride, a.offset
+                );
+            }
+            s.push_str("</td><td>");
+            for u in &draw.uniforms {
+                match u.value {
+                    CapturedValue::Floats(ref v) => { let _ = write!(s, "{}: {:?}<br>", u.location, v); },
+                    CapturedValue::Sampler { unit, ref texture } => {
+                        let _ = write!(s, "{}: unit {} {}<br>", u.location, unit, html_escape(&texture.to_string()));
+                    }
+                }
+            }
+            s.push_str("</td><td>");
+            if draw.thumbnail.is_some() {
+                let _ = write!(s, "<img src=\"draw_{}.png\">", i);
+            }
+            s.push_str("</td></tr>\n");
+        }
+        s.push_str("</table>\n</body></html>\n");
+        s
+    }
+}
+
+impl ::std::fmt::Display for ObjectName {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match self.label {
+            Some(ref label) => write!(f, "{} '{}'", self.id, label),
+            None => write!(f, "{}", self.id)
+        }
+    }
+}
+
+impl DrawCall {
+    fn write_json(&self, s: &mut String, index: usize) {
+        let _ = write!(s, "{{\"index\":{},\"program\":", index);
+        self.program.write_json(s);
+        let t = &self.target;
+        s.push_str(",\"target\":{\"framebuffer\":");
+        t.framebuffer.write_json(s);
+        let _ = write!(
+            s, ",\"rect\":[{},{},{},{}],\"viewport\":[{},{},{},{}],\"clip\":[{},{},{},{}]}}",
+            t.rect.0, t.rect.1, t.rect.2, t.rect.3,
+            t.viewport.0, t.viewport.1, t.viewport.2, t.viewport.3,
+            t.clip.0, t.clip.1, t.clip.2, t.clip.3
+        );
+        let _ = write!(s, ",\"vertex_count\":{},\"indices\":", self.vertex_count);
+        match self.indices {
+            Some(ref idx) => {
+                s.push_str("{\"buffer\":");
+                idx.buffer.write_json(s);
+                let _ = write!(
+                    s, ",\"type\":{},\"first\":{},\"base_vertex\":{}}}",
+                    idx.gltype, idx.first, idx.base_vertex
+                );
+            },
+            None => s.push_str("null")
+        }
+        s.push_str(",\"attributes\":[");
+        for (i, a) in self.attributes.iter().enumerate() {
+            if i != 0 {
+                s.push(',');
+            }
+            let _ = write!(s, "{{\"location\":{},\"buffer\":", a.location);
+            a.buffer.write_json(s);
+            let _ = write!(
+                s, ",\"components\":{},\"type\":{},\"normalized\":{},\"stride\":{},\"offset\":{}}}",
+                a.components, a.gltype, a.normalized, a.stride, a.offset
+            );
+        }
+        s.push_str("],\"uniforms\":[");
+        for (i, u) in self.uniforms.iter().enumerate() {
+            if i != 0 {
+                s.push(',');
+            }
+            let _ = write!(s, "{{\"location\":{},", u.location);
+            match u.value {
+                CapturedValue::Floats(ref v) => {
+                    s.push_str("\"floats\":[");
+                    for (j, f) in v.iter().enumerate() {
+                        if j != 0 {
+                            s.push(',');
+                        }
+                        if f.is_finite() {
+                            let _ = write!(s, "{}", f);
+                        } else {
+                            s.push_str("null");
+                        }
+                    }
+                    s.push(']');
+                },
+                CapturedValue::Sampler { unit, ref texture } => {
+                    let _ = write!(s, "\"unit\":{},\"texture\":", unit);
+                    texture.write_json(s);
+                }
+            }
+            s.push('}');
+        }
+        let _ = write!(
+            s, "],\"thumbnail\":{}}}",
+            if self.thumbnail.is_some() { format!("\"draw_{}.png\"", index) } else { "null".to_owned() }
+        );
+    }
+}
+
+impl ObjectName {
+    fn write_json(&self, s: &mut String) {
+        let _ = write!(s, "{{\"id\":{},\"label\":", self.id);
+        match self.label {
+            Some(ref label) => json_string(s, label),
+            None => s.push_str("null")
+        }
+        s.push('}');
+    }
+}
+
+fn json_string(s: &mut String, value: &str) {
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(s, "\\u{:04x}", c as u32); },
+            c => s.push(c)
+        }
+    }
+    s.push('"');
+}
+
+fn html_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(id: u32, label: Option<&str>) -> ObjectName {
+        ObjectName { id: id, label: label.map(|l| l.to_owned()) }
+    }
+
+    fn escaped(value: &str) -> String {
+        let mut s = String::new();
+        json_string(&mut s, value);
+        s
+    }
+
+    #[test]
+    fn json_strings_escape_quotes_and_backslashes() {
+        assert_eq!(escaped(r#"say "hi" \ bye"#), r#""say \"hi\" \\ bye""#);
+    }
+
+    #[test]
+    fn json_strings_escape_every_control_character() {
+        let controls: String = (0u8..0x20).map(|b| b as char).collect();
+        let json = escaped(&controls);
+        assert!(json.chars().all(|c| c as u32 >= 0x20), "{:?}", json);
+        assert!(json.starts_with(r#""\u0000\u0001"#));
+        assert!(json.contains(r#"\n"#));
+        assert!(json.ends_with(r#"\u001f""#));
+    }
+
+    #[test]
+    fn json_strings_keep_non_ascii_characters() {
+        assert_eq!(escaped("Grüße \u{1F600} \u{2028}"), "\"Grüße \u{1F600} \u{2028}\"");
+    }
+
+    #[test]
+    fn json_output_matches_the_capture() {
+        let capture = FrameCapture {
+            draws: vec![DrawCall {
+                program: name(3, Some("sprite \"batch\"")),
+                attributes: vec![CapturedAttribute {
+                    location: 0,
+                    buffer: name(5, None),
+                    components: 2,
+                    gltype: 0x1406,
+                    normalized: false,
+                    stride: 8,
+                    offset: 0
+                }],
+                uniforms: vec![
+                    CapturedUniform { location: 1, value: CapturedValue::Floats(vec![0.5, ::std::f32::NAN]) },
+                    CapturedUniform { location: 2, value: CapturedValue::Sampler { unit: 0, texture: name(7, None) } }
+                ],
+                vertex_count: 6,
+                indices: Some(CapturedIndices { buffer: name(6, None), gltype: 0x1403, first: 0, base_vertex: -2 }),
+                target: CapturedTarget {
+                    framebuffer: name(0, None),
+                    rect: (0, 0, 64, 48),
+                    viewport: (-4, 0, 72, 48),
+                    clip: (0, 0, 64, 48)
+                },
+                thumbnail: None
+            }]
+        };
+        assert_eq!(capture.to_json(), concat!(
+            r#"{"draws":[{"index":0,"program":{"id":3,"label":"sprite \"batch\""},"#,
+            r#""target":{"framebuffer":{"id":0,"label":null},"rect":[0,0,64,48],"viewport":[-4,0,72,48],"clip":[0,0,64,48]},"#,
+            r#""vertex_count":6,"indices":{"buffer":{"id":6,"label":null},"type":5123,"first":0,"base_vertex":-2},"#,
+            r#""attributes":[{"location":0,"buffer":{"id":5,"label":null},"components":2,"type":5126,"normalized":false,"stride":8,"offset":0}],"#,
+            r#""uniforms":[{"location":1,"floats":[0.5,null]},{"location":2,"unit":0,"texture":{"id":7,"label":null}}],"#,
+            r#""thumbnail":null}]}"#
+        ));
+        assert_eq!(FrameCapture::default().to_json(), r#"{"draws":[]}"#);
+    }
+}
@@ -0,0 +1,200 @@
+use std::cell::{ Cell, RefCell };
+use std::os::raw::c_void;
+use std::panic::{ self, AssertUnwindSafe };
+use std::{ process, ptr, slice };
+
+use enum_primitive::FromPrimitive;
+use gl::types::*;
+use gl;
+
+use gfx::{ Context, ResourceKind };
+
+enum_from_primitive! {
+    #[repr(u32)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    pub enum DebugSeverity {
+        High = gl::DEBUG_SEVERITY_HIGH,
+        Medium = gl::DEBUG_SEVERITY_MEDIUM,
+        Low = gl::DEBUG_SEVERITY_LOW,
+        Notification = gl::DEBUG_SEVERITY_NOTIFICATION
+    }
+}
+
+enum_from_primitive! {
+    #[repr(u32)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    pub enum DebugSource {
+        Api = gl::DEBUG_SOURCE_API,
+        WindowSystem = gl::DEBUG_SOURCE_WINDOW_SYSTEM,
+        ShaderCompiler = gl::DEBUG_SOURCE_SHADER_COMPILER,
+        ThirdParty = gl::DEBUG_SOURCE_THIRD_PARTY,
+        Application = gl::DEBUG_SOURCE_APPLICATION,
+        Other = gl::DEBUG_SOURCE_OTHER
+    }
+}
+
+enum_from_primitive! {
+    #[repr(u32)]
+    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+    pub enum DebugType {
+        Error = gl::DEBUG_TYPE_ERROR,
+        DeprecatedBehavior = gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR,
+        UndefinedBehavior = gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR,
+        Portability = gl::DEBUG_TYPE_PORTABILITY,
+        Performance = gl::DEBUG_TYPE_PERFORMANCE,
+        Marker = gl::DEBUG_TYPE_MARKER,
+        PushGroup = gl::DEBUG_TYPE_PUSH_GROUP,
+        PopGroup = gl::DEBUG_TYPE_POP_GROUP,
+        Other = gl::DEBUG_TYPE_OTHER
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    pub source: DebugSource,
+    pub kind: DebugType,
+    pub id: u32,
+    pub severity: DebugSeverity,
+    pub message: String
+}
+
+pub(crate) struct DebugOutput {
+    callback: RefCell<Option<Box<Box<Fn(&DebugMessage)>>>>,
+    check_errors: Cell<bool>
+}
+
+impl DebugOutput {
+    pub fn new() -> Self {
+        DebugOutput {
+            callback: RefCell::new(None),
+            check_errors: Cell::new(false)
+        }
+    }
+}
+
+extern "system" fn debug_callback(
+    source: GLenum, gltype: GLenum, id: GLuint, severity: GLenum,
+    length: GLsizei, message: *const GLchar, user: *mut c_void
+) {
+    // Cleared callbacks are unregistered by passing a null user pointer.
+    if user.is_null() {
+        return;
+    }
+    let callback = unsafe { &*(user as *const Box<Fn(&DebugMessage)>) };
+    let bytes = unsafe { slice::from_raw_parts(message as *const u8, length as usize) };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| callback(&DebugMessage {
+        source: DebugSource::from_u32(source).unwrap_or(DebugSource::Other),
+        kind: DebugType::from_u32(gltype).unwrap_or(DebugType::Other),
+        id: id,
+        severity: DebugSeverity::from_u32(severity).unwrap_or(DebugSeverity::Notification),
+        message: String::from_utf8_lossy(bytes).into_owned()
+    })));
+    if result.is_err() {
+        // The panic hook has already reported the panic, and unwinding into the driver is
+        // undefined behavior.
+        process::abort();
+    }
+}
+
+fn error_name(error: GLenum) -> &'static str {
+    match error {
+        gl::INVALID_ENUM => "GL_INVALID_ENUM",
+        gl::INVALID_VALUE => "GL_INVALID_VALUE",
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        _ => "Unknown GL error"
+    }
+}
+
+impl Context {
+    /// Forwards GL debug messages to `callback`. Messages are only produced by debug contexts
+    /// (see `Configuration::debug`). Without `KHR_debug`, `glGetError` is checked after each
+    /// library call instead, and errors are reported as `DebugSource::Api` messages.
+    pub fn set_debug_callback<F: Fn(&DebugMessage) + 'static>(&self, callback: F) {
+        let callback: Box<Box<Fn(&DebugMessage)>> = Box::new(Box::new(callback));
+        if self.has_debug_output() {
+            self.gl.enable(gl::DEBUG_OUTPUT);
+            self.gl.enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            // The box is kept alive until the callback is cleared or replaced.
+            unsafe {
+                self.gl.debug_message_callback(debug_callback, &*callback as *const Box<Fn(&DebugMessage)> as *const c_void);
+            }
+        } else {
+            self.debug.check_errors.set(true);
+        }
+        *self.debug.callback.borrow_mut() = Some(callback);
+    }
+
+    pub fn clear_debug_callback(&self) {
+        if self.debug.callback.borrow().is_some() && self.has_debug_output() {
+            self.gl.disable(gl::DEBUG_OUTPUT);
+            // The bindings can't pass a null callback, so unregister the callback's box before it
+            // is dropped by passing a null user pointer instead.
+            unsafe { self.gl.debug_message_callback(debug_callback, ptr::null()) };
+        }
+        self.debug.check_errors.set(false);
+        *self.debug.callback.borrow_mut() = None;
+    }
+
+    /// Whether `KHR_debug` is available, either as an extension or as part of GL 4.3.
+    pub(crate) fn has_debug_output(&self) -> bool {
+        self.capabilities.has_extension("GL_KHR_debug") || self.capabilities.supports_version(4, 3)
+    }
+
+    /// Reports any pending GL errors when falling back to `glGetError`. `call` names the library
+    /// function that was just made.
+    pub(crate) fn check_errors(&self, call: &str) {
+        if !self.debug.check_errors.get() {
+            return;
+        }
+        loop {
+            let error = self.gl.get_error();
+            if error == gl::NO_ERROR {
+                break;
+            }
+            self.report(&DebugMessage {
+                source: DebugSource::Api,
+                kind: DebugType::Error,
+                id: error,
+                severity: DebugSeverity::High,
+                message: format!("{} in {}", error_name(error), call)
+            });
+        }
+    }
+
+    /// Sends a message generated by the library to the debug callback. Returns false if there is
+    /// no callback.
+    pub(crate) fn report(&self, message: &DebugMessage) -> bool {
+        match *self.debug.callback.borrow() {
+            Some(ref callback) => {
+                callback(message);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Gives a GL object a name shown in leak reports, debug messages and graphics debuggers.
+    /// The object must have been bound at least once.
+    pub(crate) fn label_object(&self, identifier: GLenum, id: GLuint, label: &str) {
+        self.set_tracked_label(ResourceKind::from_label_identifier(identifier), id, label);
+        if !self.has_debug_output() {
+            return;
+        }
+        self.gl.object_label(identifier, id, label);
+        self.check_errors("label_object");
+    }
+}
+
+/// A debug callback that forwards messages to the `log` crate at a level matching their severity.
+#[cfg(feature = "log")]
+pub fn log_debug_message(message: &DebugMessage) {
+    let level = match message.severity {
+        DebugSeverity::High => ::log::Level::Error,
+        DebugSeverity::Medium => ::log::Level::Warn,
+        DebugSeverity::Low => ::log::Level::Info,
+        DebugSeverity::Notification => ::log::Level::Debug
+    };
+    log!(level, "{:?} {:?} {}: {}", message.source, message.kind, message.id, message.message);
+}
@@ -1,8 +1,332 @@
 use gl::types::*;
+use gl;
 
-use gfx::Context;
+use gfx::{ Context, Surface, ColorTarget, Texture2D, Renderbuffer, DepthStencilFormat, ResourceKind };
+use gfx::texture::format_name;
+
+pub enum Attachment<'a> {
+    Texture(Texture2D<'a>),
+    Renderbuffer(Renderbuffer<'a>)
+}
+
+impl<'a> Attachment<'a> {
+    pub fn texture(&self) -> Option<&Texture2D<'a>> {
+        match self {
+            Attachment::Texture(t) => Some(t),
+            Attachment::Renderbuffer(_) => None
+        }
+    }
+
+    pub fn renderbuffer(&self) -> Option<&Renderbuffer<'a>> {
+        match self {
+            Attachment::Texture(_) => None,
+            Attachment::Renderbuffer(r) => Some(r)
+        }
+    }
+
+    fn format(&self) -> GLenum {
+        match self {
+            Attachment::Texture(t) => t.format,
+            Attachment::Renderbuffer(r) => r.format
+        }
+    }
+
+    fn samples(&self) -> u32 {
+        match self {
+            Attachment::Texture(_) => 0,
+            Attachment::Renderbuffer(r) => r.samples
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        match self {
+            Attachment::Texture(t) => (t.get_width(), t.get_height()),
+            Attachment::Renderbuffer(r) => (r.get_width(), r.get_height())
+        }
+    }
+
+    /// Size, format and sample count, for error messages.
+    fn describe(&self) -> String {
+        let (w, h) = self.size();
+        let kind = match self {
+            Attachment::Texture(_) => "texture",
+            Attachment::Renderbuffer(_) => "renderbuffer"
+        };
+        format!("{}x{} {} {} with {} samples", w, h, format_name(self.format()), kind, self.samples())
+    }
+
+    /// Panics naming this attachment if it doesn't match the size and sample count of the
+    /// framebuffer.
+    fn check(&self, name: &str, width: u32, height: u32, samples: u32) {
+        assert!(
+            self.size() == (width, height) && self.samples() == samples,
+            "The {} is a {}, but the framebuffer is {}x{} with {} samples", name, self.describe(), width, height, samples
+        );
+    }
+
+    fn attach(&self, ctx: &Context, point: GLenum) {
+        match self {
+            Attachment::Texture(t) => ctx.gl.framebuffer_texture_2d(gl::FRAMEBUFFER, point, t.id),
+            Attachment::Renderbuffer(r) => ctx.gl.framebuffer_renderbuffer(gl::FRAMEBUFFER, point, r.id)
+        }
+    }
+}
+
+impl<'a> From<Texture2D<'a>> for Attachment<'a> {
+    fn from(t: Texture2D<'a>) -> Self {
+        Attachment::Texture(t)
+    }
+}
+
+impl<'a> From<Renderbuffer<'a>> for Attachment<'a> {
+    fn from(r: Renderbuffer<'a>) -> Self {
+        Attachment::Renderbuffer(r)
+    }
+}
 
 pub struct Framebuffer<'a> {
     ctx: &'a Context,
-    id: GLuint
+    id: GLuint,
+    color: Vec<Attachment<'a>>,
+    color_targets: Vec<ColorTarget>,
+    depth_stencil: Option<Attachment<'a>>,
+    samples: u32,
+    width: u32,
+    height: u32
+}
+
+impl<'a> Drop for Framebuffer<'a> {
+    fn drop(&mut self) {
+        self.ctx.delete_framebuffer(self.id);
+    }
+}
+
+impl<'a> Framebuffer<'a> {
+    pub fn surface<'b>(&'b mut self) -> Surface<'a, 'b> {
+        let has_depth = self.depth_stencil.as_ref().map_or(false, |a| a.format() != gl::STENCIL_INDEX8);
+        Surface::new(self.ctx, self.id, &self.color_targets, self.samples, has_depth, self.width, self.height)
+    }
+
+    pub fn color_attachment(&self, index: usize) -> &Attachment<'a> {
+        &self.color[index]
+    }
+
+    pub fn color_attachment_named(&self, name: &str) -> Option<&Attachment<'a>> {
+        self.color_targets.iter()
+            .position(|t| t.name.as_ref().map_or(false, |n| n == name))
+            .map(|i| &self.color[i])
+    }
+
+    /// Names the color attachments in order. Once named, each fragment output is written to the
+    /// attachment with the same name instead of the one at the same index.
+    pub fn set_color_names(&mut self, names: &[&str]) {
+        assert!(
+            names.len() == self.color.len(),
+            "{} names given for {} color attachments", names.len(), self.color.len()
+        );
+        for (i, name) in names.iter().enumerate() {
+            assert!(!names[..i].contains(name), "Color attachment name '{}' is used twice", name);
+            self.color_targets[i].name = Some(name.to_string());
+        }
+    }
+
+    pub fn depth_stencil_attachment(&self) -> Option<&Attachment<'a>> {
+        self.depth_stencil.as_ref()
+    }
+
+    pub fn into_attachments(mut self) -> (Vec<Attachment<'a>>, Option<Attachment<'a>>) {
+        (::std::mem::replace(&mut self.color, Vec::new()), self.depth_stencil.take())
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        self.ctx.label_object(gl::FRAMEBUFFER, self.id, label);
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Resolves the multisampled color attachment `index` into `to`, which must be the same size
+    /// as this framebuffer.
+    pub fn resolve(&self, index: usize, to: &mut Surface) {
+        assert!(
+            to.width == self.width && to.height == self.height,
+            "Cannot resolve a {}x{} framebuffer into a {}x{} surface", self.width, self.height, to.width, to.height
+        );
+        if self.samples == 0 || (to.x == 0 && to.y == 0) {
+            self.ctx.bind_draw_framebuffer(to.id);
+            self.resolve_into_bound(index, to.x, to.y);
+        } else {
+            // Multisample resolves need matching rectangles, so resolve at the origin of a
+            // single sampled copy and blit that into place.
+            assert!(index < self.color.len(), "Color attachment {} does not exist", index);
+            let temp_buffer = self.ctx.gl.gen_renderbuffer();
+            self.ctx.bind_renderbuffer(temp_buffer);
+            self.ctx.gl.renderbuffer_storage_multisample(0, self.color_targets[index].format, self.width as GLint, self.height as GLint);
+            let temp = self.ctx.gl.gen_framebuffer();
+            self.ctx.bind_draw_framebuffer(temp);
+            self.ctx.gl.framebuffer_renderbuffer(gl::DRAW_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, temp_buffer);
+            self.resolve_into_bound(index, 0, 0);
+
+            self.ctx.bind_read_framebuffer(temp);
+            self.ctx.bind_draw_framebuffer(to.id);
+            self.ctx.gl.blit_framebuffer(
+                [0, 0, self.width as GLint, self.height as GLint],
+                [to.x as GLint, to.y as GLint, (to.x + self.width) as GLint, (to.y + self.height) as GLint],
+                gl::COLOR_BUFFER_BIT, gl::NEAREST
+            );
+            self.ctx.bind_framebuffer(0);
+            self.ctx.delete_framebuffer(temp);
+            self.ctx.delete_renderbuffer(temp_buffer);
+        }
+        self.ctx.bind_framebuffer(to.id);
+        self.ctx.check_errors("Framebuffer::resolve");
+    }
+
+    pub fn resolve_to_texture(&self, index: usize, to: &mut Texture2D) {
+        assert!(
+            to.get_width() == self.width && to.get_height() == self.height,
+            "Cannot resolve a {}x{} framebuffer into a {}x{} texture", self.width, self.height, to.get_width(), to.get_height()
+        );
+        let temp = self.ctx.gl.gen_framebuffer();
+        self.ctx.bind_draw_framebuffer(temp);
+        self.ctx.gl.framebuffer_texture_2d(gl::DRAW_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, to.id);
+        self.resolve_into_bound(index, 0, 0);
+        self.ctx.bind_framebuffer(0);
+        self.ctx.delete_framebuffer(temp);
+        self.ctx.check_errors("Framebuffer::resolve_to_texture");
+    }
+
+    fn resolve_into_bound(&self, index: usize, x: u32, y: u32) {
+        assert!(index < self.color.len(), "Color attachment {} does not exist", index);
+        self.ctx.bind_read_framebuffer(self.id);
+        self.ctx.gl.read_buffer(gl::COLOR_ATTACHMENT0 + index as GLenum);
+        self.ctx.gl.blit_framebuffer(
+            [0, 0, self.width as GLint, self.height as GLint],
+            [x as GLint, y as GLint, (x + self.width) as GLint, (y + self.height) as GLint],
+            gl::COLOR_BUFFER_BIT, gl::NEAREST
+        );
+        self.ctx.gl.read_buffer(gl::COLOR_ATTACHMENT0);
+    }
+}
+
+impl Context {
+    pub fn create_framebuffer<'a>(&'a self, color_attachments: Vec<Attachment<'a>>, depth_stencil: Option<Attachment<'a>>) -> Framebuffer<'a> {
+        let (width, height) = match (color_attachments.first(), &depth_stencil) {
+            (Some(a), _) | (None, Some(a)) => a.size(),
+            (None, None) => panic!("A framebuffer needs at least one attachment")
+        };
+        let samples = match (color_attachments.first(), &depth_stencil) {
+            (Some(a), _) | (None, Some(a)) => a.samples(),
+            (None, None) => unreachable!()
+        };
+        let max_color = self.capabilities().max_color_attachments.min(self.capabilities().max_draw_buffers);
+        assert!(
+            color_attachments.len() <= max_color as usize,
+            "{} color attachments given, but at most {} are supported", color_attachments.len(), max_color
+        );
+
+        let id = self.gl.gen_framebuffer();
+        self.bind_framebuffer(id);
+        self.track(ResourceKind::Framebuffer, id);
+        let fb = Framebuffer {
+            ctx: self,
+            id: id,
+            color_targets: color_attachments.iter().map(|a| ColorTarget { format: a.format(), name: None }).collect(),
+            color: color_attachments,
+            depth_stencil: depth_stencil,
+            samples: samples,
+            width: width,
+            height: height
+        };
+
+        for (i, a) in fb.color.iter().enumerate() {
+            let name = format!("color attachment {}", i);
+            assert!(
+                DepthStencilFormat::from_gl(a.format()).is_none(),
+                "The {} is a {}, which is not a color format", name, a.describe()
+            );
+            a.check(&name, width, height, samples);
+            a.attach(self, gl::COLOR_ATTACHMENT0 + i as GLenum);
+        }
+
+        let draw_buffers: Vec<GLenum> = (0..fb.color.len()).map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum).collect();
+        self.set_draw_buffers(id, &draw_buffers);
+        if fb.color.is_empty() {
+            self.gl.read_buffer(gl::NONE);
+        }
+
+        if let Some(ref ds) = fb.depth_stencil {
+            let point = match DepthStencilFormat::from_gl(ds.format()) {
+                Some(f) => f.attachment_point(),
+                None => panic!("The depth/stencil attachment is a {}, which is not a depth or stencil format", ds.describe())
+            };
+            let name = match point {
+                gl::DEPTH_ATTACHMENT => "depth attachment",
+                gl::STENCIL_ATTACHMENT => "stencil attachment",
+                _ => "depth/stencil attachment"
+            };
+            ds.check(name, width, height, samples);
+            ds.attach(self, point);
+        }
+
+        let status = self.gl.check_framebuffer_status(gl::FRAMEBUFFER);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            let mut attachments: Vec<String> = fb.color.iter().enumerate()
+                .map(|(i, a)| format!("color attachment {}: {}", i, a.describe()))
+                .collect();
+            if let Some(ref ds) = fb.depth_stencil {
+                attachments.push(format!("depth/stencil attachment: {}", ds.describe()));
+            }
+            panic!("Framebuffer is incomplete: {}\n{}", match status {
+                gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT",
+                gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT",
+                gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+                gl::FRAMEBUFFER_UNSUPPORTED => "GL_FRAMEBUFFER_UNSUPPORTED (this combination of attachment formats is not supported)",
+                _ => "Unknown Status"
+            }, attachments.join("\n"));
+        }
+        self.check_errors("create_framebuffer");
+        fb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gl;
+    use gfx::{ Context, TextureFormat };
+    use gfx::backend::{ RecordingBackend, Arg };
+
+    #[test]
+    fn multisample_resolves_into_offset_surfaces_go_through_a_copy() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let msaa = ctx.create_framebuffer(vec![ctx.create_multisample_renderbuffer(4, 4, TextureFormat::Rgba8, 4).into()], None);
+        let mut target = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+        let framebuffers = rec.live_objects("Framebuffer");
+        let renderbuffers = rec.live_objects("Renderbuffer");
+        rec.clear_calls();
+
+        msaa.resolve(0, &mut target.surface().subsurface(4, 4, 4, 4));
+        let rects: Vec<Vec<Arg>> = rec.calls_to("BlitFramebuffer").into_iter().map(|c| c.args[..2].to_vec()).collect();
+        assert_eq!(rects, vec![
+            vec![Arg::Ints(vec![0, 0, 4, 4]), Arg::Ints(vec![0, 0, 4, 4])],
+            vec![Arg::Ints(vec![0, 0, 4, 4]), Arg::Ints(vec![4, 4, 8, 8])]
+        ]);
+        assert_eq!(rec.calls_to("RenderbufferStorageMultisample")[0].args[..2], [Arg::Int(0), Arg::Int(gl::RGBA8 as i64)]);
+        assert_eq!(rec.live_objects("Framebuffer"), framebuffers);
+        assert_eq!(rec.live_objects("Renderbuffer"), renderbuffers);
+
+        rec.clear_calls();
+        msaa.resolve(0, &mut target.surface().subsurface(0, 0, 4, 4));
+        assert_eq!(rec.calls_to("BlitFramebuffer").len(), 1, "Resolves at the origin need no copy");
+    }
 }
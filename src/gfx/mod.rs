@@ -1,15 +1,58 @@
+use std::cell::Cell;
+
 use gl::types::*;
 use gl;
 
+pub(crate) mod backend;
+use self::backend::{ Backend, GlBackend };
+
+mod capabilities;
+pub use self::capabilities::Capabilities;
+
+mod state;
+pub use self::state::StateStats;
+use self::state::GlState;
+
+mod debug;
+pub use self::debug::{ DebugMessage, DebugSeverity, DebugSource, DebugType };
+#[cfg(feature = "log")]
+pub use self::debug::log_debug_message;
+use self::debug::DebugOutput;
+
+mod registry;
+pub use self::registry::{ ResourceKind, LiveResource };
+use self::registry::Registry;
+pub(crate) use self::registry::format_size;
+
+mod capture;
+pub use self::capture::*;
+use self::capture::CaptureState;
+
+mod vertex_array;
+use self::vertex_array::VertexArrayCache;
+
 mod texture;
 pub use self::texture::*;
 
 mod framebuffer;
-pub(crate) use self::framebuffer::*;
+pub use self::framebuffer::*;
+
+mod renderbuffer;
+pub use self::renderbuffer::*;
 
 mod surface;
 pub use self::surface::*;
 
+mod split;
+pub use self::split::{ Size, Margins };
+pub(crate) use self::split::distribute;
+
+mod virtual_screen;
+pub use self::virtual_screen::*;
+
+mod query;
+pub use self::query::*;
+
 mod buffer;
 pub use self::buffer::*;
 
@@ -22,31 +65,108 @@ pub mod shader;
 pub use self::shader::{ Program, GlslDataType, glsl_type };
 
 pub struct Context {
+    pub(crate) gl: Box<Backend>,
+    capabilities: Capabilities,
+    state: GlState,
+    vertex_arrays: VertexArrayCache,
+    debug: DebugOutput,
+    registry: Registry,
+    capture: CaptureState,
+    last_frame: Cell<SavedFrame>
+}
+
+#[derive(Copy, Clone)]
+struct SavedFrame {
+    framebuffer: GLuint,
+    renderbuffer: GLuint,
+    width: u32,
+    height: u32
 }
 
 impl Context {
     pub (crate) fn create() -> Self {
-        unsafe {
-            let mut vao = 0;
-            gl::GenVertexArrays(1, &mut vao);
-            gl::BindVertexArray(vao);
-        }
-        Self {  }
+        Self::with_backend(Box::new(GlBackend))
     }
 
-    pub(crate) fn bind_framebuffer(&self, id: GLuint) {
-        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, id) };
+    /// Creates a context that makes its GL calls through `backend`, such as a
+    /// `backend::RecordingBackend` for testing rendering code without a window.
+    pub(crate) fn with_backend(backend: Box<Backend>) -> Self {
+        let capabilities = Capabilities::query(&*backend);
+        Self {
+            gl: backend,
+            state: GlState::new(capabilities.max_combined_texture_units),
+            capabilities: capabilities,
+            vertex_arrays: VertexArrayCache::new(),
+            debug: DebugOutput::new(),
+            registry: Registry::new(),
+            capture: CaptureState::new(),
+            last_frame: Cell::new(SavedFrame {
+                framebuffer: 0,
+                renderbuffer: 0,
+                width: 0,
+                height: 0
+            })
+        }
     }
 
-    pub(crate) fn bind_array_buffer(&self, id: GLuint) {
-        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, id) };
+    pub(crate) fn save_frame(&self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            // The window is minimised; keep the last frame that had something in it.
+            return;
+        }
+        let mut saved = self.last_frame.get();
+        if saved.framebuffer == 0 {
+            saved.framebuffer = self.gl.gen_framebuffer();
+            saved.renderbuffer = self.gl.gen_renderbuffer();
+        }
+        if saved.width != width || saved.height != height {
+            self.bind_renderbuffer(saved.renderbuffer);
+            self.gl.renderbuffer_storage_multisample(0, gl::RGBA8, width as GLint, height as GLint);
+            self.bind_framebuffer(saved.framebuffer);
+            self.gl.framebuffer_renderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, saved.renderbuffer);
+            saved.width = width;
+            saved.height = height;
+        }
+        self.bind_read_framebuffer(0);
+        self.bind_draw_framebuffer(saved.framebuffer);
+        self.gl.blit_framebuffer(
+            [0, 0, width as GLint, height as GLint],
+            [0, 0, width as GLint, height as GLint],
+            gl::COLOR_BUFFER_BIT, gl::NEAREST
+        );
+        self.bind_framebuffer(0);
+        self.last_frame.set(saved);
+        self.check_errors("save_frame");
     }
 
-    pub(crate) fn bind_texture_2d(&self, id: GLuint) {
-        unsafe { gl::BindTexture(gl::TEXTURE_2D, id) };
+    /// Stretches the last saved frame over `to`, returning false if there was nothing to draw.
+    pub(crate) fn present_saved_frame(&self, to: &mut Surface) -> bool {
+        let saved = self.last_frame.get();
+        if saved.framebuffer == 0 || saved.width == 0 || saved.height == 0 || to.width == 0 || to.height == 0 {
+            return false;
+        }
+        to.bind();
+        self.bind_read_framebuffer(saved.framebuffer);
+        self.gl.blit_framebuffer(
+            [0, 0, saved.width as GLint, saved.height as GLint],
+            [to.x as GLint, to.y as GLint, (to.x + to.width) as GLint, (to.y + to.height) as GLint],
+            gl::COLOR_BUFFER_BIT, gl::LINEAR
+        );
+        self.bind_framebuffer(to.id);
+        self.check_errors("present_saved_frame");
+        true
     }
+}
 
-    pub (crate) fn use_program(&self, id: GLuint) {
-        unsafe { gl::UseProgram(id) };
+impl Drop for Context {
+    fn drop(&mut self) {
+        self.report_leaks();
+        self.clear_debug_callback();
+        self.delete_vertex_arrays();
+        let saved = self.last_frame.get();
+        if saved.framebuffer != 0 {
+            self.delete_framebuffer(saved.framebuffer);
+            self.delete_renderbuffer(saved.renderbuffer);
+        }
     }
 }
@@ -0,0 +1,168 @@
+use gl::types::*;
+use gl;
+
+use gfx::{ Context, ResourceKind };
+
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum QueryKind {
+    /// Nanoseconds of GPU time spent between `begin` and `end`.
+    TimeElapsed = gl::TIME_ELAPSED,
+    /// The GPU clock in nanoseconds when `record_timestamp` was reached.
+    Timestamp = gl::TIMESTAMP,
+    /// The number of samples that passed the depth and stencil tests.
+    SamplesPassed = gl::SAMPLES_PASSED,
+    /// 1 if any sample passed the depth and stencil tests, otherwise 0.
+    AnySamplesPassed = gl::ANY_SAMPLES_PASSED
+}
+
+pub struct Query<'a> {
+    ctx: &'a Context,
+    id: GLuint,
+    kind: QueryKind,
+    active: bool,
+    issued: bool
+}
+
+impl<'a> Drop for Query<'a> {
+    fn drop(&mut self) {
+        if self.active {
+            self.ctx.end_query(self.kind as GLenum);
+        }
+        self.ctx.delete_query(self.id);
+    }
+}
+
+impl<'a> Query<'a> {
+    pub fn get_kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    /// Starts counting. Only one query of each kind can be active at a time.
+    pub fn begin(&mut self) {
+        assert!(self.kind != QueryKind::Timestamp, "Timestamp queries are recorded with record_timestamp");
+        assert!(!self.active, "Query is already active");
+        self.ctx.begin_query(self.kind as GLenum, self.id);
+        self.active = true;
+    }
+
+    pub fn end(&mut self) {
+        assert!(self.active, "Query is not active");
+        self.ctx.end_query(self.kind as GLenum);
+        self.active = false;
+        self.issued = true;
+    }
+
+    /// Runs `f` between `begin` and `end`.
+    pub fn measure<R, F: FnOnce() -> R>(&mut self, f: F) -> R {
+        self.begin();
+        let result = f();
+        self.end();
+        result
+    }
+
+    pub fn record_timestamp(&mut self) {
+        assert!(self.kind == QueryKind::Timestamp, "Only timestamp queries can record a timestamp");
+        self.ctx.gl.query_counter(self.id);
+        self.issued = true;
+    }
+
+    /// Returns the result of the last completed query if the GPU has finished it, without
+    /// waiting. Results are usually available a frame or two after the query ends.
+    pub fn poll(&self) -> Option<u64> {
+        if self.active || !self.issued {
+            return None;
+        }
+        if self.ctx.gl.get_query_result_available(self.id) {
+            Some(self.ctx.gl.get_query_result(self.id))
+        } else {
+            None
+        }
+    }
+
+    /// Waits for the GPU to finish the query and returns its result.
+    pub fn wait(&self) -> u64 {
+        assert!(!self.active, "Query is still active");
+        assert!(self.issued, "Query has never been run");
+        self.ctx.gl.get_query_result(self.id)
+    }
+}
+
+/// A fixed number of queries used round robin, so a query can be started every frame while the
+/// results of earlier frames are collected without stalling.
+pub struct QueryRing<'a> {
+    queries: Vec<Query<'a>>,
+    next: usize,
+    pending: usize
+}
+
+impl<'a> QueryRing<'a> {
+    /// Starts the next query in the ring. Panics if every query is still waiting to be collected.
+    pub fn begin(&mut self) {
+        assert!(self.pending < self.queries.len(), "All queries in the ring are pending");
+        self.queries[self.next].begin();
+    }
+
+    pub fn end(&mut self) {
+        self.queries[self.next].end();
+        self.advance();
+    }
+
+    pub fn measure<R, F: FnOnce() -> R>(&mut self, f: F) -> R {
+        self.begin();
+        let result = f();
+        self.end();
+        result
+    }
+
+    pub fn record_timestamp(&mut self) {
+        assert!(self.pending < self.queries.len(), "All queries in the ring are pending");
+        self.queries[self.next].record_timestamp();
+        self.advance();
+    }
+
+    /// Returns the oldest pending result if it is available.
+    pub fn poll(&mut self) -> Option<u64> {
+        if self.pending == 0 {
+            return None;
+        }
+        let oldest = (self.next + self.queries.len() - self.pending) % self.queries.len();
+        let result = self.queries[oldest].poll();
+        if result.is_some() {
+            self.pending -= 1;
+        }
+        result
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    fn advance(&mut self) {
+        self.next = (self.next + 1) % self.queries.len();
+        self.pending += 1;
+    }
+}
+
+impl Context {
+    pub fn create_query(&self, kind: QueryKind) -> Query {
+        let id = self.gl.gen_query();
+        self.track(ResourceKind::Query, id);
+        Query {
+            ctx: self,
+            id: id,
+            kind: kind,
+            active: false,
+            issued: false
+        }
+    }
+
+    pub fn create_query_ring(&self, kind: QueryKind, size: usize) -> QueryRing {
+        assert!(size > 0, "A query ring needs at least one query");
+        QueryRing {
+            queries: (0..size).map(|_| self.create_query(kind)).collect(),
+            next: 0,
+            pending: 0
+        }
+    }
+}
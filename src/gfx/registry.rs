@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gl::types::*;
+use gl;
+
+use gfx::{ Context, DebugMessage, DebugSource, DebugType, DebugSeverity };
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    Renderbuffer,
+    Framebuffer,
+    Program,
+    Query
+}
+
+impl ResourceKind {
+    pub(crate) fn from_label_identifier(identifier: GLenum) -> Self {
+        match identifier {
+            gl::BUFFER => ResourceKind::Buffer,
+            gl::TEXTURE => ResourceKind::Texture,
+            gl::RENDERBUFFER => ResourceKind::Renderbuffer,
+            gl::FRAMEBUFFER => ResourceKind::Framebuffer,
+            gl::PROGRAM => ResourceKind::Program,
+            gl::QUERY => ResourceKind::Query,
+            _ => unreachable!()
+        }
+    }
+}
+
+/// A GL object created through the `Context` that has not been dropped yet.
+#[derive(Clone, Debug)]
+pub struct LiveResource {
+    pub kind: ResourceKind,
+    pub id: u32,
+    /// Approximate GPU memory used by the object's storage.
+    pub bytes: usize,
+    pub label: Option<String>
+}
+
+pub(crate) struct Registry {
+    live: RefCell<HashMap<(ResourceKind, GLuint), LiveResource>>
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            live: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+/// Approximate bytes per pixel of an internal format.
+pub(crate) fn format_size(format: GLenum) -> usize {
+    match format {
+        gl::R8 | gl::STENCIL_INDEX8 => 1,
+        gl::RG8 | gl::R16F | gl::DEPTH_COMPONENT16 => 2,
+        gl::RGB8 => 3,
+        gl::RG16F => 4,
+        gl::RGBA16F | gl::RG32F | gl::DEPTH32F_STENCIL8 => 8,
+        gl::RGBA32F | gl::RGBA32I | gl::RGBA32UI => 16,
+        _ => 4
+    }
+}
+
+impl Context {
+    pub(crate) fn track(&self, kind: ResourceKind, id: GLuint) {
+        self.registry.live.borrow_mut().insert((kind, id), LiveResource {
+            kind: kind,
+            id: id,
+            bytes: 0,
+            label: None
+        });
+    }
+
+    pub(crate) fn untrack(&self, kind: ResourceKind, id: GLuint) {
+        self.registry.live.borrow_mut().remove(&(kind, id));
+    }
+
+    pub(crate) fn set_tracked_size(&self, kind: ResourceKind, id: GLuint, bytes: usize) {
+        if let Some(r) = self.registry.live.borrow_mut().get_mut(&(kind, id)) {
+            r.bytes = bytes;
+        }
+    }
+
+    pub(crate) fn set_tracked_label(&self, kind: ResourceKind, id: GLuint, label: &str) {
+        if let Some(r) = self.registry.live.borrow_mut().get_mut(&(kind, id)) {
+            r.label = Some(label.to_owned());
+        }
+    }
+
+    pub(crate) fn tracked_label(&self, kind: ResourceKind, id: GLuint) -> Option<String> {
+        self.registry.live.borrow().get(&(kind, id)).and_then(|r| r.label.clone())
+    }
+
+    /// Lists every live resource, ordered by kind and then by id.
+    pub fn live_resources(&self) -> Vec<LiveResource> {
+        let mut live: Vec<_> = self.registry.live.borrow().values().cloned().collect();
+        live.sort_by_key(|r| (r.kind as u32, r.id));
+        live
+    }
+
+    pub fn live_count(&self, kind: ResourceKind) -> usize {
+        self.registry.live.borrow().values().filter(|r| r.kind == kind).count()
+    }
+
+    pub fn live_bytes(&self, kind: ResourceKind) -> usize {
+        self.registry.live.borrow().values().filter(|r| r.kind == kind).map(|r| r.bytes).sum()
+    }
+
+    /// Reports resources that are still alive as the `Context` is dropped, which only happens if
+    /// they were leaked with `mem::forget` or a reference cycle. Reports go to the debug callback
+    /// if one is set, otherwise to the `log` crate as warnings when the `log` feature is enabled
+    /// and to stderr when it isn't.
+    pub(crate) fn report_leaks(&self) {
+        for r in self.live_resources() {
+            let message = match r.label {
+                Some(ref label) => format!("Leaked {:?} {} '{}' ({} bytes)", r.kind, r.id, label, r.bytes),
+                None => format!("Leaked {:?} {} ({} bytes)", r.kind, r.id, r.bytes)
+            };
+            let message = DebugMessage {
+                source: DebugSource::Application,
+                kind: DebugType::Other,
+                id: r.id,
+                severity: DebugSeverity::Medium,
+                message: message
+            };
+            if !self.report(&message) {
+                #[cfg(feature = "log")]
+                ::gfx::log_debug_message(&message);
+                #[cfg(not(feature = "log"))]
+                eprintln!("{}", message.message);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::marker::PhantomData;
 use std::mem::size_of;
+use std::ops::Range;
 
 use gl::types::*;
 use gl;
@@ -7,23 +8,14 @@ use gl;
 use cgmath::*;
 
 use tlprog::{ TLNatural, TLOption, TLSome, TLNone };
-use gfx::{ Context, VertexBuffer, Surface, Texture2D };
+use gfx::{ Context, VertexBuffer, IndexBuffer, Index, Surface, Texture2D, ResourceKind };
+use gfx::{ DrawCall, CapturedAttribute, CapturedIndices, CapturedUniform, CapturedValue };
+use gfx::vertex_array::AttribPointerData;
 use gfx::vertex;
 use gfx::shader;
-use gfx::shader::GlslDataType;
+use gfx::shader::{ GlslDataType, ProgramOutput };
 use gfx::shader::glsl_type;
 
-#[derive(Debug)]
-struct AttribPointerData {
-    buf_id: GLuint,
-    index: GLuint,
-    size: GLint,
-    gltype: GLenum,
-    normalized: GLboolean,
-    stride: GLint,
-    offset: usize
-}
-
 pub enum UniformData {
     Float(f32),
     Vec2(Vector2<f32>),
@@ -35,11 +27,25 @@ pub enum UniformData {
     Sampler2D(GLuint)
 }
 
+/// The index buffer range drawn by an indexed command.
+struct IndexData {
+    buf_id: GLuint,
+    gltype: GLenum,
+    size: usize,
+    first: usize,
+    range: (u32, u32),
+    base_vertex: i32
+}
+
 pub struct RenderCommand<'a: 'b, 'b, L: vertex::Layout + 'b, UL: shader::UniformList + 'b, C: TLOption<i32>> {
     ctx: &'a Context,
     bindings: Vec<AttribPointerData>,
+    /// The length of the shortest attached vertex buffer.
+    min_elems: usize,
     uniforms: Vec<(GLint, UniformData)>,
     shader: GLuint,
+    outputs: &'b [ProgramOutput],
+    indices: Option<IndexData>,
     remaining_layout: L,
     remaining_uniforms: UL,
     vertex_count: C,
@@ -51,8 +57,11 @@ impl<'a, 'b, L: vertex::Layout, UL: shader::UniformList> RenderCommand<'a, 'b, L
         RenderCommand {
             ctx: shader.ctx,
             bindings: Vec::new(),
+            min_elems: usize::max_value(),
             uniforms: Vec::new(),
             shader: shader.id,
+            outputs: &shader.outputs,
+            indices: None,
             remaining_layout: shader.layout,
             remaining_uniforms: shader.uniforms,
             vertex_count: TLNone,
@@ -63,26 +72,78 @@ impl<'a, 'b, L: vertex::Layout, UL: shader::UniformList> RenderCommand<'a, 'b, L
 
 impl<'a: 'b, 'b> RenderCommand<'a, 'b, vertex::layout::Nil, shader::uniform::Nil, TLSome<i32>> {
     pub fn execute(&self, to: &mut Surface) {
-        unsafe {
-            to.bind();
-            self.ctx.use_program(self.shader);
-            for binding in &self.bindings {
-                self.ctx.bind_array_buffer(binding.buf_id);
-                gl::VertexAttribPointer(binding.index, binding.size, binding.gltype, binding.normalized, binding.stride, binding.offset as *const GLvoid);
-                gl::EnableVertexAttribArray(binding.index);
-            }
-            let mut active_tex = 1;
-            for (index, data) in &self.uniforms {
-                data.submit(*index, &mut active_tex);
-            }
-            gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count.0);
+        to.bind();
+        to.bind_fragment_outputs(self.outputs);
+        to.enable_scissor();
+        self.ctx.use_program(self.shader);
+        self.ctx.bind_vertex_array_for(&self.bindings, self.indices.as_ref().map_or(0, |i| i.buf_id));
+        let mut active_tex = 1;
+        for (index, data) in &self.uniforms {
+            data.submit(self.ctx, *index, &mut active_tex);
+        }
+        match self.indices {
+            Some(ref i) => self.draw_indexed(i),
+            None => self.ctx.gl.draw_arrays(gl::TRIANGLES, 0, self.vertex_count.0)
+        }
+        self.ctx.set_scissor_test(false);
+        self.ctx.check_errors("RenderCommand::execute");
+        if self.ctx.is_capturing() {
+            self.capture(to);
+        }
+    }
 
-            gl::ActiveTexture(gl::TEXTURE0);
-            for binding in &self.bindings {
-                gl::DisableVertexAttribArray(binding.index);
-            }
+    fn draw_indexed(&self, i: &IndexData) {
+        let (start, end) = i.range;
+        if self.vertex_count.0 > 0 {
+            assert!(
+                start as i64 + i.base_vertex as i64 >= 0,
+                "Index {} with base vertex {} is before the start of the vertex buffers", start, i.base_vertex
+            );
+            assert!(
+                (end as i64 + i.base_vertex as i64) < self.min_elems as i64,
+                "Index {} with base vertex {} is out of bounds for vertex buffers of length {}",
+                end, i.base_vertex, self.min_elems
+            );
+        }
+        let offset = i.first * i.size;
+        if i.base_vertex == 0 {
+            self.ctx.gl.draw_range_elements(gl::TRIANGLES, start, end, self.vertex_count.0, i.gltype, offset);
+        } else {
+            self.ctx.gl.draw_range_elements_base_vertex(
+                gl::TRIANGLES, start, end, self.vertex_count.0, i.gltype, offset, i.base_vertex
+            );
         }
     }
+
+    fn capture(&self, to: &Surface) {
+        let ctx = self.ctx;
+        let mut unit = 1;
+        ctx.record_draw(DrawCall {
+            program: ctx.object_name(ResourceKind::Program, self.shader),
+            attributes: self.bindings.iter().map(|b| CapturedAttribute {
+                location: b.index,
+                buffer: ctx.object_name(ResourceKind::Buffer, b.buf_id),
+                components: b.size,
+                gltype: b.gltype,
+                normalized: b.normalized,
+                stride: b.stride,
+                offset: b.offset
+            }).collect(),
+            uniforms: self.uniforms.iter().map(|&(location, ref data)| CapturedUniform {
+                location: location,
+                value: data.capture(ctx, &mut unit)
+            }).collect(),
+            vertex_count: self.vertex_count.0,
+            indices: self.indices.as_ref().map(|i| CapturedIndices {
+                buffer: ctx.object_name(ResourceKind::Buffer, i.buf_id),
+                gltype: i.gltype,
+                first: i.first,
+                base_vertex: i.base_vertex
+            }),
+            target: to.capture_target(),
+            thumbnail: if ctx.capture_thumbnails() && to.can_read_pixels() { Some(to.read_pixels()) } else { None }
+        });
+    }
 }
 
 impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> RenderCommand<'a, 'b, L, UL, C> {
@@ -97,26 +158,31 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
 
     {
         let (idx, r) = self.remaining_layout.pluck();
+        let vertex_count = match (self.vertex_count.reify(), &self.indices) {
+            (Some(l), Some(_)) => l,
+            (Some(l), None) => l.min(buf.elems as i32),
+            (None, _) => buf.elems as i32
+        };
         self.bindings.push(AttribPointerData {
             buf_id: buf.id,
             index: idx as u32,
             size: F::COMPONENTS,
             gltype: F::GL_TYPE,
-            normalized: F::NORMALIZED,
+            normalized: F::NORMALIZED != gl::FALSE,
             stride: size_of::<S>() as i32,
             offset: S::OFFSET
         });
         RenderCommand {
             ctx: self.ctx,
             bindings: self.bindings,
+            min_elems: self.min_elems.min(buf.elems),
             uniforms: self.uniforms,
             shader: self.shader,
+            outputs: self.outputs,
+            indices: self.indices,
             remaining_layout: r,
             remaining_uniforms: self.remaining_uniforms,
-            vertex_count: TLSome(match self.vertex_count.reify() {
-                Some(l) => l.min(buf.elems as i32),
-                None => buf.elems as i32
-            }),
+            vertex_count: TLSome(vertex_count),
             _phantom: PhantomData
         }
     }
@@ -125,8 +191,11 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
         RenderCommand {
             ctx: self.ctx,
             bindings: self.bindings,
+            min_elems: self.min_elems,
             uniforms: self.uniforms,
             shader: self.shader,
+            outputs: self.outputs,
+            indices: self.indices,
             remaining_layout: self.remaining_layout,
             remaining_uniforms: self.remaining_uniforms,
             vertex_count: TLSome(match self.vertex_count.reify() {
@@ -137,6 +206,49 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
         }
     }
 
+    /// Draws the triangles listed in `buf` instead of consecutive vertices. The vertex count
+    /// becomes the number of indices; call `limit_vertices` afterwards to draw fewer.
+    pub fn indices<I: Index>(self, buf: &'b IndexBuffer<'a, I>) -> RenderCommand<'a, 'b, L, UL, TLSome<i32>> {
+        self.index_range(buf, 0..buf.elems)
+    }
+
+    /// Like `indices`, but only draws the indices in `range`.
+    pub fn index_range<I: Index>(self, buf: &'b IndexBuffer<'a, I>, range: Range<usize>) -> RenderCommand<'a, 'b, L, UL, TLSome<i32>> {
+        assert!(
+            range.start <= range.end && range.end <= buf.elems,
+            "Index range {:?} is out of bounds for an index buffer of length {}", range, buf.elems
+        );
+        RenderCommand {
+            ctx: self.ctx,
+            bindings: self.bindings,
+            min_elems: self.min_elems,
+            uniforms: self.uniforms,
+            shader: self.shader,
+            outputs: self.outputs,
+            indices: Some(IndexData {
+                buf_id: buf.id,
+                gltype: I::GL_TYPE,
+                size: size_of::<I>(),
+                first: range.start,
+                range: buf.bounds(range.clone()),
+                base_vertex: self.indices.map_or(0, |i| i.base_vertex)
+            }),
+            remaining_layout: self.remaining_layout,
+            remaining_uniforms: self.remaining_uniforms,
+            vertex_count: TLSome((range.end - range.start) as i32),
+            _phantom: self._phantom
+        }
+    }
+
+    /// Adds `base` to every index before it is used to fetch vertices.
+    pub fn base_vertex(mut self, base: i32) -> Self {
+        match self.indices {
+            Some(ref mut i) => i.base_vertex = base,
+            None => panic!("base_vertex requires an index buffer to be set with indices first")
+        }
+        self
+    }
+
     pub fn uniform_data<U, I>(mut self, _name: U, value: <<U as shader::Uniform>::Type as GlslDataType>::Data) -> RenderCommand<'a, 'b, L, UL::Remainder, C>
     where
         U: shader::Uniform,
@@ -149,8 +261,11 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
         RenderCommand {
             ctx: self.ctx,
             bindings: self.bindings,
+            min_elems: self.min_elems,
             uniforms: self.uniforms,
             shader: self.shader,
+            outputs: self.outputs,
+            indices: self.indices,
             remaining_layout: self.remaining_layout,
             remaining_uniforms: r,
             vertex_count: self.vertex_count,
@@ -169,8 +284,11 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
         RenderCommand {
             ctx: self.ctx,
             bindings: self.bindings,
+            min_elems: self.min_elems,
             uniforms: self.uniforms,
             shader: self.shader,
+            outputs: self.outputs,
+            indices: self.indices,
             remaining_layout: self.remaining_layout,
             remaining_uniforms: r,
             vertex_count: self.vertex_count,
@@ -180,23 +298,259 @@ impl<'a: 'b, 'b, L: vertex::Layout, UL: shader::UniformList, C: TLOption<i32>> R
 }
 
 impl UniformData {
-    fn submit(&self, index: GLint, active_tex: &mut u32) {
-        unsafe {
-            match self {
-                UniformData::Float(v) => gl::Uniform1f(index, *v),
-                UniformData::Vec2(v) => gl::Uniform2f(index, v.x, v.y),
-                UniformData::Vec3(v) => gl::Uniform3f(index, v.x, v.y, v.z),
-                UniformData::Vec4(v) => gl::Uniform4f(index, v.x, v.y, v.z, v.w),
-                UniformData::Mat2(v) => gl::UniformMatrix2fv(index, 1, gl::FALSE, v.as_ptr()),
-                UniformData::Mat3(v) => gl::UniformMatrix3fv(index, 1, gl::FALSE, v.as_ptr()),
-                UniformData::Mat4(v) => gl::UniformMatrix4fv(index, 1, gl::FALSE, v.as_ptr()),
-                UniformData::Sampler2D(i) => {
-                    gl::Uniform1i(index, *active_tex as GLint);
-                    gl::ActiveTexture(gl::TEXTURE0 + *active_tex);
-                    *active_tex += 1;
-                    gl::BindTexture(gl::TEXTURE_2D, *i);
-                }
+    fn capture(&self, ctx: &Context, unit: &mut u32) -> CapturedValue {
+        match self {
+            UniformData::Float(v) => CapturedValue::Floats(vec![*v]),
+            UniformData::Vec2(v) => CapturedValue::Floats(AsRef::<[f32; 2]>::as_ref(v).to_vec()),
+            UniformData::Vec3(v) => CapturedValue::Floats(AsRef::<[f32; 3]>::as_ref(v).to_vec()),
+            UniformData::Vec4(v) => CapturedValue::Floats(AsRef::<[f32; 4]>::as_ref(v).to_vec()),
+            UniformData::Mat2(v) => CapturedValue::Floats(AsRef::<[f32; 4]>::as_ref(v).to_vec()),
+            UniformData::Mat3(v) => CapturedValue::Floats(AsRef::<[f32; 9]>::as_ref(v).to_vec()),
+            UniformData::Mat4(v) => CapturedValue::Floats(AsRef::<[f32; 16]>::as_ref(v).to_vec()),
+            UniformData::Sampler2D(i) => {
+                *unit += 1;
+                CapturedValue::Sampler { unit: *unit - 1, texture: ctx.object_name(ResourceKind::Texture, *i) }
+            }
+        }
+    }
+
+    fn submit(&self, ctx: &Context, index: GLint, active_tex: &mut u32) {
+        match self {
+            UniformData::Float(v) => ctx.gl.uniform_1f(index, *v),
+            UniformData::Vec2(v) => ctx.gl.uniform_2f(index, v.x, v.y),
+            UniformData::Vec3(v) => ctx.gl.uniform_3f(index, v.x, v.y, v.z),
+            UniformData::Vec4(v) => ctx.gl.uniform_4f(index, v.x, v.y, v.z, v.w),
+            UniformData::Mat2(v) => ctx.gl.uniform_matrix(index, 2, AsRef::<[f32; 4]>::as_ref(v)),
+            UniformData::Mat3(v) => ctx.gl.uniform_matrix(index, 3, AsRef::<[f32; 9]>::as_ref(v)),
+            UniformData::Mat4(v) => ctx.gl.uniform_matrix(index, 4, AsRef::<[f32; 16]>::as_ref(v)),
+            UniformData::Sampler2D(i) => {
+                assert!(
+                    *active_tex < ctx.capabilities().max_texture_units,
+                    "Too many samplers: only {} texture units are available", ctx.capabilities().max_texture_units - 1
+                );
+                ctx.gl.uniform_1i(index, *active_tex as GLint);
+                ctx.bind_texture_unit(*active_tex, *i);
+                *active_tex += 1;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec2;
+
+    use { vertex_attributes, uniforms, shader_program, vertex_struct_type, vertex_struct };
+    use gfx::{ Context, RenderCommand, TextureFormat, glsl_type };
+    use gfx::backend::{ RecordingBackend, Call, Arg };
+    use gfx::vertex::format;
+
+    vertex_attributes!(Pos: glsl_type::Vec2);
+    uniforms!(Scale: glsl_type::Float, Tex: glsl_type::Sampler2D);
+
+    type Vertex = vertex_struct_type!(Pos: format::FloatVec2);
+
+    macro_rules! test_program {
+        ($ctx:expr) => {
+            shader_program!($ctx, (pos: Pos) => vertex {
+                uniform scale: Scale;
+                code { "void main() { gl_Position = vec4(pos * scale, 0.0, 1.0); }" }
+            } -> () => fragment {
+                uniform tex: Tex;
+                code { "void main() { color = texture(tex, vec2(0.0)); }" }
+            } -> (color: glsl_type::Vec4))
+        };
+    }
+
+    fn vertices(n: usize) -> Vec<Vertex> {
+        (0..n).map(|i| vertex_struct!(Pos: vec2(i as f32, 0.0))).collect()
+    }
+
+    fn call(name: &'static str, args: Vec<Arg>) -> Call {
+        Call { name: name, args: args }
+    }
+
+    fn ints(values: &[i64]) -> Vec<Arg> {
+        values.iter().map(|&v| Arg::Int(v)).collect()
+    }
+
+    #[test]
+    fn execute_binds_sets_uniforms_and_draws() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let program = test_program!(ctx);
+        let texture = ctx.create_empty_texture_2d(4, 4, TextureFormat::Rgba8);
+        let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(3));
+        rec.clear_calls();
+
+        let command = RenderCommand::new(&program).attach(&buf, Pos).uniform_data(Scale, 2.0).uniform_sampler_2d(Tex, &texture);
+        command.execute(&mut fb.surface());
+        let vao = match rec.calls_to("GenVertexArrays")[0].args[0] {
+            Arg::Int(id) => id,
+            ref other => panic!("Unexpected vertex array name {:?}", other)
+        };
+        assert_eq!(rec.calls(), vec![
+            call("Viewport", ints(&[0, 0, 8, 8])),
+            call("Scissor", ints(&[0, 0, 8, 8])),
+            call("Enable", ints(&[gl::SCISSOR_TEST as i64])),
+            call("UseProgram", ints(&[program.id as i64])),
+            call("GenVertexArrays", ints(&[vao])),
+            call("BindVertexArray", ints(&[vao])),
+            call("VertexAttribPointer", ints(&[0, 2, gl::FLOAT as i64, 0, 8, 0])),
+            call("EnableVertexAttribArray", ints(&[0])),
+            call("Uniform1f", vec![Arg::Int(0), Arg::Float(2.0)]),
+            call("Uniform1i", ints(&[1, 1])),
+            call("ActiveTexture", ints(&[1])),
+            call("BindTexture", ints(&[gl::TEXTURE_2D as i64, texture.id as i64])),
+            call("DrawArrays", ints(&[gl::TRIANGLES as i64, 0, 3])),
+            call("Disable", ints(&[gl::SCISSOR_TEST as i64]))
+        ]);
+
+        // Drawing again reuses the cached program, vertex array and texture binding.
+        rec.clear_calls();
+        command.execute(&mut fb.surface());
+        let names: Vec<&str> = rec.calls().iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["Enable", "Uniform1f", "Uniform1i", "DrawArrays", "Disable"]);
+    }
+
+    #[test]
+    fn indexed_draws_pass_the_index_range_and_base_vertex() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let program = test_program!(ctx);
+        let texture = ctx.create_empty_texture_2d(4, 4, TextureFormat::Rgba8);
+        let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(6));
+        let mut indices = ctx.create_index_buffer::<u16>();
+        indices.allocate(&[0, 1, 2, 1, 3, 2]);
+        rec.clear_calls();
+
+        RenderCommand::new(&program).indices(&indices).attach(&buf, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+        RenderCommand::new(&program).indices(&indices).limit_vertices(3).attach(&buf, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+        RenderCommand::new(&program).index_range(&indices, 3..6).attach(&buf, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+        RenderCommand::new(&program).index_range(&indices, 3..6).base_vertex(2).attach(&buf, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+
+        let (triangles, short) = (gl::TRIANGLES as i64, gl::UNSIGNED_SHORT as i64);
+        assert_eq!(rec.calls_to("DrawRangeElements"), vec![
+            call("DrawRangeElements", ints(&[triangles, 0, 3, 6, short, 0])),
+            call("DrawRangeElements", ints(&[triangles, 0, 3, 3, short, 0])),
+            call("DrawRangeElements", ints(&[triangles, 1, 3, 3, short, 6]))
+        ]);
+        assert_eq!(rec.calls_to("DrawRangeElementsBaseVertex"), vec![
+            call("DrawRangeElementsBaseVertex", ints(&[triangles, 1, 3, 3, short, 6, 2]))
+        ]);
+        assert_eq!(rec.calls_to("GenVertexArrays").len(), 1, "All draws should share one vertex array");
+    }
+
+    #[test]
+    fn meshes_sharing_an_index_buffer_are_checked_against_their_own_indices() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let program = test_program!(ctx);
+        let texture = ctx.create_empty_texture_2d(4, 4, TextureFormat::Rgba8);
+        let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+        let mut big = ctx.create_vertex_buffer::<Vertex>();
+        big.allocate(&vertices(100));
+        let mut small = ctx.create_vertex_buffer::<Vertex>();
+        small.allocate(&vertices(103));
+        let mut indices = ctx.create_index_buffer::<u16>();
+        indices.allocate(&[0, 50, 99, 0, 1, 2]);
+        rec.clear_calls();
+
+        RenderCommand::new(&program).index_range(&indices, 0..3).attach(&big, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+        RenderCommand::new(&program).index_range(&indices, 3..6).base_vertex(100).attach(&small, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+
+        let (triangles, short) = (gl::TRIANGLES as i64, gl::UNSIGNED_SHORT as i64);
+        assert_eq!(rec.calls_to("DrawRangeElements"), vec![call("DrawRangeElements", ints(&[triangles, 0, 99, 3, short, 0]))]);
+        assert_eq!(rec.calls_to("DrawRangeElementsBaseVertex"), vec![
+            call("DrawRangeElementsBaseVertex", ints(&[triangles, 0, 2, 3, short, 6, 100]))
+        ]);
+    }
+
+    fn draw_indexed(vertex_count: usize, base_vertex: i32) {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let program = test_program!(ctx);
+        let texture = ctx.create_empty_texture_2d(4, 4, TextureFormat::Rgba8);
+        let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+        let mut buf = ctx.create_vertex_buffer::<Vertex>();
+        buf.allocate(&vertices(vertex_count));
+        let mut indices = ctx.create_index_buffer::<u16>();
+        indices.allocate(&[0, 1, 2, 1, 3, 2]);
+        RenderCommand::new(&program).indices(&indices).base_vertex(base_vertex).attach(&buf, Pos)
+            .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+            .execute(&mut fb.surface());
+    }
+
+    #[test]
+    fn indices_may_reach_the_last_vertex() {
+        draw_indexed(4, 0);
+        draw_indexed(6, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds for vertex buffers of length 3")]
+    fn indices_past_the_vertex_buffer_panic() {
+        draw_indexed(3, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds for vertex buffers of length 6")]
+    fn base_vertex_past_the_vertex_buffer_panics() {
+        draw_indexed(6, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "before the start of the vertex buffers")]
+    fn negative_base_vertex_before_the_vertex_buffer_panics() {
+        draw_indexed(6, -1);
+    }
+
+    #[test]
+    fn dropping_resources_deletes_them() {
+        let rec = RecordingBackend::new();
+        {
+            let ctx = Context::with_backend(Box::new(rec.clone()));
+            let program = test_program!(ctx);
+            let texture = ctx.create_empty_texture_2d(4, 4, TextureFormat::Rgba8);
+            let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into()], None);
+            let mut buf = ctx.create_vertex_buffer::<Vertex>();
+            buf.allocate(&vertices(3));
+            let mut indices = ctx.create_index_buffer::<u16>();
+            indices.allocate(&[0, 1, 2]);
+            RenderCommand::new(&program).indices(&indices).attach(&buf, Pos)
+                .uniform_data(Scale, 1.0).uniform_sampler_2d(Tex, &texture)
+                .execute(&mut fb.surface());
+
+            assert_eq!(rec.live_objects("Shader"), 0, "Shaders should be deleted once the program is linked");
+            for &(kind, live) in &[("Program", 1), ("Texture", 2), ("Framebuffer", 1), ("Buffer", 2), ("VertexArray", 1)] {
+                assert_eq!(rec.live_objects(kind), live, "{} objects before dropping", kind);
+            }
+
+            let buf_id = buf.id;
+            drop(buf);
+            assert_eq!(rec.calls_to("DeleteBuffers"), vec![call("DeleteBuffers", ints(&[buf_id as i64]))]);
+            assert_eq!(rec.live_objects("VertexArray"), 0, "Vertex arrays using a deleted buffer should be deleted");
+            drop(fb);
+            assert_eq!(rec.live_objects("Framebuffer"), 0);
+            assert_eq!(rec.live_objects("Texture"), 1);
+        }
+        for kind in &["Program", "Texture", "Framebuffer", "Renderbuffer", "Buffer", "VertexArray", "Shader", "Query"] {
+            assert_eq!(rec.live_objects(kind), 0, "{} objects left after dropping the context", kind);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use gl::types::*;
+use gl;
+
+use gfx::{ Context, TextureFormat, DepthStencilFormat, ResourceKind, format_size };
+
+pub trait RenderbufferFormat : Copy {
+    fn internal_format(self) -> GLenum;
+}
+
+impl RenderbufferFormat for TextureFormat {
+    fn internal_format(self) -> GLenum { self as GLenum }
+}
+
+impl RenderbufferFormat for DepthStencilFormat {
+    fn internal_format(self) -> GLenum { self as GLenum }
+}
+
+pub struct Renderbuffer<'a> {
+    ctx: &'a Context,
+    pub(crate) id: GLuint,
+    pub(crate) format: GLenum,
+    pub(crate) samples: u32,
+    width: u32,
+    height: u32
+}
+
+impl<'a> Drop for Renderbuffer<'a> {
+    fn drop(&mut self) {
+        self.ctx.delete_renderbuffer(self.id);
+    }
+}
+
+impl<'a> Renderbuffer<'a> {
+    pub(crate) fn bind(&self) {
+        self.ctx.bind_renderbuffer(self.id);
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        self.ctx.label_object(gl::RENDERBUFFER, self.id, label);
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+impl Context {
+    pub fn create_renderbuffer<F: RenderbufferFormat>(&self, width: u32, height: u32, format: F) -> Renderbuffer {
+        self.create_multisample_renderbuffer(width, height, format, 0)
+    }
+
+    /// A `samples` count of 0 creates a regular single-sampled renderbuffer. The implementation may
+    /// allocate more samples than requested.
+    pub fn create_multisample_renderbuffer<F: RenderbufferFormat>(&self, width: u32, height: u32, format: F, samples: u32) -> Renderbuffer {
+        let caps = self.capabilities();
+        assert!(
+            width <= caps.max_renderbuffer_size && height <= caps.max_renderbuffer_size,
+            "A {}x{} renderbuffer exceeds the maximum renderbuffer size of {}", width, height, caps.max_renderbuffer_size
+        );
+        assert!(samples <= caps.max_samples, "{} samples requested, but at most {} are supported", samples, caps.max_samples);
+        let id = self.gl.gen_renderbuffer();
+        self.track(ResourceKind::Renderbuffer, id);
+        let mut rb = Renderbuffer {
+            ctx: self,
+            id: id,
+            format: format.internal_format(),
+            samples: samples,
+            width: width,
+            height: height
+        };
+        rb.bind();
+        self.gl.renderbuffer_storage_multisample(samples as GLsizei, rb.format, width as GLint, height as GLint);
+        rb.samples = self.gl.get_renderbuffer_samples() as u32;
+        let bytes = width as usize * height as usize * format_size(rb.format) * rb.samples.max(1) as usize;
+        self.set_tracked_size(ResourceKind::Renderbuffer, id, bytes);
+        self.check_errors("create_renderbuffer");
+        rb
+    }
+}
@@ -5,6 +5,20 @@ pub trait GlslType : Copy {
     const TYPE_STRING: &'static str;
 }
 
+/// The type of each component of a fragment output or color attachment.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ComponentType {
+    /// Floating point, including normalized integer formats.
+    Float,
+    Int,
+    UInt
+}
+
+pub trait FragmentOutputType : GlslType {
+    const COMPONENTS: u32;
+    const COMPONENT_TYPE: ComponentType;
+}
+
 pub trait GlslDataType : GlslType {
     type Data;
     fn into_uniform_data(v: Self::Data) -> UniformData;
@@ -19,6 +33,10 @@ impl GlslDataType for Float {
     type Data = f32;
     fn into_uniform_data(v: f32) -> UniformData { UniformData::Float(v) }
 }
+impl FragmentOutputType for Float {
+    const COMPONENTS: u32 = 1;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Float;
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vec2;
@@ -29,6 +47,10 @@ impl GlslDataType for Vec2 {
     type Data = Vector2<f32>;
     fn into_uniform_data(v: Vector2<f32>) -> UniformData { UniformData::Vec2(v) }
 }
+impl FragmentOutputType for Vec2 {
+    const COMPONENTS: u32 = 2;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Float;
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vec3;
@@ -39,6 +61,10 @@ impl GlslDataType for Vec3 {
     type Data = Vector3<f32>;
     fn into_uniform_data(v: Vector3<f32>) -> UniformData { UniformData::Vec3(v) }
 }
+impl FragmentOutputType for Vec3 {
+    const COMPONENTS: u32 = 3;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Float;
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vec4;
@@ -49,6 +75,90 @@ impl GlslDataType for Vec4 {
     type Data = Vector4<f32>;
     fn into_uniform_data(v: Vector4<f32>) -> UniformData { UniformData::Vec4(v) }
 }
+impl FragmentOutputType for Vec4 {
+    const COMPONENTS: u32 = 4;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Float;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Int;
+impl GlslType for Int {
+    const TYPE_STRING: &'static str = "int";
+}
+impl FragmentOutputType for Int {
+    const COMPONENTS: u32 = 1;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Int;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct IVec2;
+impl GlslType for IVec2 {
+    const TYPE_STRING: &'static str = "ivec2";
+}
+impl FragmentOutputType for IVec2 {
+    const COMPONENTS: u32 = 2;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Int;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct IVec3;
+impl GlslType for IVec3 {
+    const TYPE_STRING: &'static str = "ivec3";
+}
+impl FragmentOutputType for IVec3 {
+    const COMPONENTS: u32 = 3;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Int;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct IVec4;
+impl GlslType for IVec4 {
+    const TYPE_STRING: &'static str = "ivec4";
+}
+impl FragmentOutputType for IVec4 {
+    const COMPONENTS: u32 = 4;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Int;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UInt;
+impl GlslType for UInt {
+    const TYPE_STRING: &'static str = "uint";
+}
+impl FragmentOutputType for UInt {
+    const COMPONENTS: u32 = 1;
+    const COMPONENT_TYPE: ComponentType = ComponentType::UInt;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UVec2;
+impl GlslType for UVec2 {
+    const TYPE_STRING: &'static str = "uvec2";
+}
+impl FragmentOutputType for UVec2 {
+    const COMPONENTS: u32 = 2;
+    const COMPONENT_TYPE: ComponentType = ComponentType::UInt;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UVec3;
+impl GlslType for UVec3 {
+    const TYPE_STRING: &'static str = "uvec3";
+}
+impl FragmentOutputType for UVec3 {
+    const COMPONENTS: u32 = 3;
+    const COMPONENT_TYPE: ComponentType = ComponentType::UInt;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UVec4;
+impl GlslType for UVec4 {
+    const TYPE_STRING: &'static str = "uvec4";
+}
+impl FragmentOutputType for UVec4 {
+    const COMPONENTS: u32 = 4;
+    const COMPONENT_TYPE: ComponentType = ComponentType::UInt;
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct Mat2;
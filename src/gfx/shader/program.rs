@@ -1,98 +1,122 @@
 use std::marker::PhantomData;
-use std::ffi::CString;
-use std::ptr;
 
 use gl::types::*;
 use gl;
 
-use gfx::{ Context };
+use gfx::{ Context, ResourceKind };
 use gfx::vertex;
-use gfx::shader::{ UniformList, UniformListBuilder };
+use gfx::shader::{ UniformList, UniformListBuilder, ComponentType };
+
+#[derive(Copy, Clone, Debug)]
+pub struct FragmentOutput<'s> {
+    pub name: &'s str,
+    pub components: u32,
+    pub component_type: ComponentType
+}
+
+/// A fragment output of a linked program. Output `i` is bound to fragment color number `i`.
+#[derive(Clone, Debug)]
+pub(crate) struct ProgramOutput {
+    pub name: String,
+    pub components: u32,
+    pub component_type: ComponentType
+}
 
 pub struct Program<'a, L: vertex::Layout, UL: UniformList> {
     pub (crate) ctx: &'a Context,
     pub (crate) id: GLuint,
+    pub (crate) outputs: Vec<ProgramOutput>,
     pub (crate) layout: L,
     pub (crate) uniforms: UL,
     _phantom: PhantomData<UL>
 }
 
 impl Context {
+    /// Creates a program whose fragment outputs are left to the driver to place. Use
+    /// `create_shader_program_with_outputs` to draw to more than one color attachment.
     pub fn create_shader_program<'a, L, UL>(&'a self, layout: L, uniforms: UL, vs_code: &str, fs_code: &str) -> Program<'a, L::Layout, UL::UniformList>
     where
         L: vertex::LayoutBuilder,
         UL: UniformListBuilder
     {
-        unsafe fn compile_shader(shader_type: GLenum, code: &str) -> GLuint {
-            let shader = gl::CreateShader(shader_type);
-
-            let c_code = ::ffi::to_cstring(code);
-            let p = c_code.as_ptr();
-            gl::ShaderSource(shader, 1, &p, ptr::null());
-            gl::CompileShader(shader);
-
-            let mut status = 0;
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
-
-            if status == gl::FALSE as i32 {
-                let mut info_log_length = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut info_log_length);
-                let c_info_log = CString::new(Vec::with_capacity(info_log_length as usize)).unwrap().into_raw();
-                gl::GetShaderInfoLog(shader, info_log_length, ptr::null_mut(), c_info_log);
-                panic!("Failed to compile shader: {}", CString::from_raw(c_info_log).to_string_lossy());
+        self.create_shader_program_with_outputs(layout, uniforms, &[], vs_code, fs_code)
+    }
+
+    /// Creates a program with `outputs[i]` bound to draw buffer `i`.
+    pub fn create_shader_program_with_outputs<'a, L, UL>(&'a self, layout: L, uniforms: UL, outputs: &[FragmentOutput], vs_code: &str, fs_code: &str) -> Program<'a, L::Layout, UL::UniformList>
+    where
+        L: vertex::LayoutBuilder,
+        UL: UniformListBuilder
+    {
+        let compile_shader = |shader_type: GLenum, code: &str| -> GLuint {
+            let shader = self.gl.create_shader(shader_type);
+            self.gl.shader_source(shader, code);
+            self.gl.compile_shader(shader);
+            if !self.gl.get_compile_status(shader) {
+                panic!("Failed to compile shader: {}", self.gl.get_shader_info_log(shader));
             }
             shader
-        }
+        };
 
-        let id = unsafe {
-            let vs = compile_shader(gl::VERTEX_SHADER, vs_code);
-            let fs = compile_shader(gl::FRAGMENT_SHADER, fs_code);
+        assert!(
+            outputs.len() <= self.capabilities().max_draw_buffers as usize,
+            "{} fragment outputs declared, but at most {} are supported", outputs.len(), self.capabilities().max_draw_buffers
+        );
 
-            let program = gl::CreateProgram();
-            gl::AttachShader(program, vs);
-            gl::AttachShader(program, fs);
+        let vs = compile_shader(gl::VERTEX_SHADER, vs_code);
+        let fs = compile_shader(gl::FRAGMENT_SHADER, fs_code);
 
-            gl::LinkProgram(program);
+        let id = self.gl.create_program();
+        self.gl.attach_shader(id, vs);
+        self.gl.attach_shader(id, fs);
 
-            let mut status = 0;
-            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+        for (i, output) in outputs.iter().enumerate() {
+            self.gl.bind_frag_data_location(id, i as GLuint, output.name);
+        }
 
-            if status == gl::FALSE as i32 {
-                let mut info_log_length = 0;
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut info_log_length);
-                let c_info_log = CString::new(Vec::with_capacity(info_log_length as usize)).unwrap().into_raw();
-                gl::GetProgramInfoLog(program, info_log_length, ptr::null_mut(), c_info_log);
-                panic!("Failed to link shader program: {}", CString::from_raw(c_info_log).to_string_lossy());
-            }
+        self.gl.link_program(id);
 
-            gl::DeleteShader(vs);
-            gl::DeleteShader(fs);
-            program
-        };
+        if !self.gl.get_link_status(id) {
+            panic!("Failed to link shader program: {}", self.gl.get_program_info_log(id));
+        }
+
+        self.gl.delete_shader(vs);
+        self.gl.delete_shader(fs);
+        self.track(ResourceKind::Program, id);
+        self.check_errors("create_shader_program");
 
         Program {
             ctx: self,
-            layout: layout.into(|s| unsafe {
-                let loc = gl::GetAttribLocation(id, ::ffi::to_cstring(s).as_ptr());
+            layout: layout.into(|s| {
+                let loc = self.gl.get_attrib_location(id, s);
                 assert_ne!(loc, -1, "Attribute '{}' not found in the shader program", s);
                 loc
             }),
-            uniforms: uniforms.into(|s| unsafe {
-                let loc = gl::GetUniformLocation(id, ::ffi::to_cstring(s).as_ptr());
+            uniforms: uniforms.into(|s| {
+                let loc = self.gl.get_uniform_location(id, s);
                 assert_ne!(loc, -1, "Uniform '{}' not found in the shader program", s);
                 loc
             }),
             id: id,
+            outputs: outputs.iter().map(|o| ProgramOutput {
+                name: o.name.to_owned(),
+                components: o.components,
+                component_type: o.component_type
+            }).collect(),
             _phantom: PhantomData
         }
     }
 }
 
+impl<'a, L: vertex::Layout, UL: UniformList> Program<'a, L, UL> {
+    pub fn set_label(&mut self, label: &str) {
+        self.ctx.label_object(gl::PROGRAM, self.id, label);
+    }
+}
+
 impl<'a, L: vertex::Layout, UL: UniformList> Drop for Program<'a, L, UL> {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.id);
-        }
+        self.ctx.delete_program(self.id);
     }
 }
 
@@ -114,7 +138,7 @@ macro_rules! shader_program {
     } {
         $(uniform $name2:ident: $t2:ty;)*
         code $_2:tt
-    }) => { shader_program!(@uniformlist $($name1: $t1;)* $($name2: $t2;)*); };
+    }) => { shader_program!(@uniformlist $($name1: $t1;)* $($name2: $t2;)*) };
     (@uniformlist) => { $crate::gfx::shader::uniform::BuilderNil };
     (@uniformlist $name:ident: $type:ty;) => {{
         let l: $crate::gfx::shader::uniform::BuilderCons<$type, _> = $crate::gfx::shader::uniform::BuilderCons::new(stringify!($name), shader_program!(@uniformlist));
@@ -125,6 +149,14 @@ macro_rules! shader_program {
         l
     }};
 
+    (@outputs ($($o_name:ident: $o_t:ty),*)) => {
+        &[$($crate::gfx::shader::FragmentOutput {
+            name: stringify!($o_name),
+            components: <$o_t as $crate::gfx::shader::FragmentOutputType>::COMPONENTS,
+            component_type: <$o_t as $crate::gfx::shader::FragmentOutputType>::COMPONENT_TYPE
+        }),*]
+    };
+
     (@vcode ($($i_name:ident: $i_t:ty),*) $a:tt $b:tt) => {
         shader_program!(@code ($($i_name: <$i_t as $crate::gfx::vertex::Attribute>::Type),*) $a $b)
     };
@@ -146,9 +178,10 @@ macro_rules! shader_program {
     };
 
     ($ctx:expr, $vinput:tt => vertex $vs_code:tt -> $finput:tt => fragment $fs_code:tt -> $foutput:tt) => {
-        $ctx.create_shader_program(
+        $ctx.create_shader_program_with_outputs(
             shader_program!(@layout $vinput),
             shader_program!(@uniforms $vs_code $fs_code),
+            shader_program!(@outputs $foutput),
             shader_program!(@vcode $vinput $vs_code $finput),
             shader_program!(@code $finput $fs_code $foutput)
         )
@@ -0,0 +1,178 @@
+use gfx::Surface;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Size {
+    /// An exact number of pixels.
+    Pixels(u32),
+    /// A fraction of the space left after spacing is removed.
+    Fraction(f32),
+    /// A share of whatever space is left over after `Pixels` and `Fraction` sizes, weighted
+    /// against the other `Flex` sizes.
+    Flex(f32)
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Margins {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32
+}
+
+impl Margins {
+    pub fn uniform(margin: u32) -> Self {
+        Margins {
+            left: margin,
+            right: margin,
+            top: margin,
+            bottom: margin
+        }
+    }
+}
+
+/// Divides `total` pixels into `(offset, length)` spans according to `sizes`, with `spacing`
+/// pixels between consecutive spans. Spans are clamped so they never extend past `total`.
+pub(crate) fn distribute(total: u32, sizes: &[Size], spacing: u32) -> Vec<(u32, u32)> {
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+    let available = total.saturating_sub(spacing.saturating_mul(sizes.len() as u32 - 1));
+
+    let mut fixed = 0;
+    let mut total_flex = 0.0;
+    for size in sizes {
+        match *size {
+            Size::Pixels(p) => fixed = p.saturating_add(fixed),
+            Size::Fraction(f) => fixed = ((f.max(0.0) * available as f32).round() as u32).saturating_add(fixed),
+            Size::Flex(w) => total_flex += w.max(0.0)
+        }
+    }
+    let flex_space = available.saturating_sub(fixed);
+
+    let mut spans = Vec::with_capacity(sizes.len());
+    let mut offset = 0;
+    let mut flex_before = 0.0;
+    for size in sizes {
+        let length = match *size {
+            Size::Pixels(p) => p,
+            Size::Fraction(f) => (f.max(0.0) * available as f32).round() as u32,
+            Size::Flex(w) if total_flex > 0.0 => {
+                // Round the running total rather than each span so the flex spans add up exactly.
+                let start = (flex_before / total_flex * flex_space as f32).round() as u32;
+                flex_before += w.max(0.0);
+                let end = (flex_before / total_flex * flex_space as f32).round() as u32;
+                end - start
+            }
+            Size::Flex(_) => 0
+        };
+        let offset_clamped = offset.min(total);
+        spans.push((offset_clamped, length.min(total - offset_clamped)));
+        offset = offset_clamped.saturating_add(length).saturating_add(spacing);
+    }
+    spans
+}
+
+/// Shrinks a `width` by `height` area by `margins`, returning `(x, y, width, height)` with `y`
+/// measured from the bottom. Margins larger than the area leave it empty.
+fn inset_rect(width: u32, height: u32, margins: Margins) -> (u32, u32, u32, u32) {
+    let x = margins.left.min(width);
+    let y = margins.bottom.min(height);
+    (x, y, width - x - margins.right.min(width - x), height - y - margins.top.min(height - y))
+}
+
+/// Lays out equally sized cells as `(x, y, width, height)` with `y` measured from the bottom,
+/// ordered left to right, then top to bottom.
+fn grid_cells(width: u32, height: u32, columns: u32, rows: u32, spacing: u32) -> Vec<(u32, u32, u32, u32)> {
+    let xs = distribute(width, &vec![Size::Flex(1.0); columns as usize], spacing);
+    let ys = distribute(height, &vec![Size::Flex(1.0); rows as usize], spacing);
+    let mut cells = Vec::with_capacity(xs.len() * ys.len());
+    for &(y, cell_height) in &ys {
+        for &(x, cell_width) in &xs {
+            cells.push((x, height - y - cell_height, cell_width, cell_height));
+        }
+    }
+    cells
+}
+
+impl<'a, 'b> Surface<'a, 'b> {
+    pub fn inset<'c>(&'c mut self, margins: Margins) -> Surface<'a, 'c> where 'b: 'c {
+        let (x, y, width, height) = inset_rect(self.width, self.height, margins);
+        self.region(x, y, width, height)
+    }
+
+    /// Splits this surface into side-by-side panels, from left to right.
+    pub fn split_row<'c>(&'c mut self, sizes: &[Size], spacing: u32) -> Vec<Surface<'a, 'c>> where 'b: 'c {
+        distribute(self.width, sizes, spacing).into_iter()
+            .map(|(x, width)| self.region(x, 0, width, self.height))
+            .collect()
+    }
+
+    /// Splits this surface into stacked panels, from top to bottom.
+    pub fn split_column<'c>(&'c mut self, sizes: &[Size], spacing: u32) -> Vec<Surface<'a, 'c>> where 'b: 'c {
+        distribute(self.height, sizes, spacing).into_iter()
+            .map(|(y, height)| self.region(0, self.height - y - height, self.width, height))
+            .collect()
+    }
+
+    /// Splits this surface into equally sized cells, ordered left to right, then top to bottom.
+    pub fn grid<'c>(&'c mut self, columns: u32, rows: u32, spacing: u32) -> Vec<Surface<'a, 'c>> where 'b: 'c {
+        grid_cells(self.width, self.height, columns, rows, spacing).into_iter()
+            .map(|(x, y, width, height)| self.region(x, y, width, height))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ distribute, inset_rect, grid_cells, Size, Margins };
+
+    #[test]
+    fn mixed_sizes_fill_the_total() {
+        let sizes = [Size::Pixels(20), Size::Fraction(0.25), Size::Flex(1.0), Size::Flex(3.0)];
+        assert_eq!(distribute(100, &sizes, 0), vec![(0, 20), (20, 25), (45, 14), (59, 41)]);
+    }
+
+    #[test]
+    fn spacing_is_removed_before_fractions_and_flex() {
+        assert_eq!(distribute(100, &[Size::Flex(1.0); 3], 5), vec![(0, 30), (35, 30), (70, 30)]);
+        assert_eq!(distribute(110, &[Size::Fraction(0.5), Size::Flex(1.0)], 10), vec![(0, 50), (60, 50)]);
+    }
+
+    #[test]
+    fn overflowing_spans_are_clamped_to_the_total() {
+        let sizes = [Size::Pixels(30), Size::Pixels(30), Size::Pixels(30)];
+        assert_eq!(distribute(50, &sizes, 0), vec![(0, 30), (30, 20), (50, 0)]);
+        assert_eq!(distribute(20, &[Size::Pixels(10), Size::Pixels(10)], 5), vec![(0, 10), (15, 5)]);
+        assert_eq!(distribute(50, &[Size::Pixels(60), Size::Flex(1.0)], 0), vec![(0, 50), (50, 0)]);
+    }
+
+    #[test]
+    fn undersized_spans_leave_space_at_the_end() {
+        assert_eq!(distribute(100, &[Size::Pixels(10), Size::Fraction(0.2)], 0), vec![(0, 10), (10, 20)]);
+    }
+
+    #[test]
+    fn degenerate_inputs_do_not_overflow() {
+        assert_eq!(distribute(10, &[], 5), vec![]);
+        assert_eq!(distribute(10, &[Size::Flex(0.0)], 0), vec![(0, 0)]);
+        assert_eq!(distribute(10, &[Size::Flex(1.0); 3], ::std::u32::MAX), vec![(0, 0), (10, 0), (10, 0)]);
+        assert_eq!(distribute(10, &[Size::Pixels(::std::u32::MAX); 2], 0), vec![(0, 10), (10, 0)]);
+    }
+
+    #[test]
+    fn inset_removes_margins_from_each_side() {
+        let margins = Margins { left: 10, right: 20, top: 5, bottom: 15 };
+        assert_eq!(inset_rect(100, 50, margins), (10, 15, 70, 30));
+        assert_eq!(inset_rect(100, 50, Margins::uniform(80)), (80, 50, 0, 0));
+        assert_eq!(inset_rect(100, 50, Margins::default()), (0, 0, 100, 50));
+    }
+
+    #[test]
+    fn grid_cells_start_at_the_top_left() {
+        assert_eq!(grid_cells(100, 60, 2, 2, 10), vec![
+            (0, 35, 45, 25), (55, 35, 45, 25),
+            (0, 0, 45, 25), (55, 0, 45, 25)
+        ]);
+        assert_eq!(grid_cells(100, 60, 0, 2, 10), vec![]);
+    }
+}
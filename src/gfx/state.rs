@@ -0,0 +1,266 @@
+use std::cell::{ Cell, RefCell };
+use std::collections::HashMap;
+
+use gl::types::*;
+use gl;
+
+use gfx::{ Context, ResourceKind };
+
+/// Counts of state changing GL calls made through the `Context`, and of calls that were skipped
+/// because the state was already set.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateStats {
+    pub issued: u64,
+    pub skipped: u64
+}
+
+/// A shadow copy of the GL state the library changes. `None` means unknown.
+pub(crate) struct GlState {
+    read_framebuffer: Cell<Option<GLuint>>,
+    draw_framebuffer: Cell<Option<GLuint>>,
+    array_buffer: Cell<Option<GLuint>>,
+    copy_write_buffer: Cell<Option<GLuint>>,
+    vertex_array: Cell<Option<GLuint>>,
+    renderbuffer: Cell<Option<GLuint>>,
+    program: Cell<Option<GLuint>>,
+    active_texture: Cell<Option<GLuint>>,
+    textures: Vec<Cell<Option<GLuint>>>,
+    viewport: Cell<Option<(GLint, GLint, GLsizei, GLsizei)>>,
+    scissor: Cell<Option<(GLint, GLint, GLsizei, GLsizei)>>,
+    scissor_test: Cell<Option<bool>>,
+    queries: [Cell<Option<GLuint>>; 3],
+    /// The `glDrawBuffers` list of each framebuffer, which is framebuffer state.
+    draw_buffers: RefCell<HashMap<GLuint, Vec<GLenum>>>,
+    stats: Cell<StateStats>
+}
+
+impl GlState {
+    pub fn new(texture_units: u32) -> Self {
+        GlState {
+            read_framebuffer: Cell::new(None),
+            draw_framebuffer: Cell::new(None),
+            array_buffer: Cell::new(None),
+            copy_write_buffer: Cell::new(None),
+            vertex_array: Cell::new(None),
+            renderbuffer: Cell::new(None),
+            program: Cell::new(None),
+            active_texture: Cell::new(None),
+            textures: (0..texture_units).map(|_| Cell::new(None)).collect(),
+            viewport: Cell::new(None),
+            scissor: Cell::new(None),
+            scissor_test: Cell::new(None),
+            queries: Default::default(),
+            draw_buffers: RefCell::new(HashMap::new()),
+            stats: Cell::new(StateStats::default())
+        }
+    }
+
+    /// Sets `cache` to `value`, calling `f` only if it wasn't already set.
+    fn set<T: Copy + PartialEq, F: FnOnce()>(&self, cache: &Cell<Option<T>>, value: T, f: F) {
+        let mut stats = self.stats.get();
+        if cache.get() == Some(value) {
+            stats.skipped += 1;
+        } else {
+            f();
+            cache.set(Some(value));
+            stats.issued += 1;
+        }
+        self.stats.set(stats);
+    }
+
+    /// GL resets bindings of deleted objects to 0, so forget about them to avoid skipping a bind
+    /// when the name is reused.
+    fn forget(cache: &Cell<Option<GLuint>>, id: GLuint) {
+        if cache.get() == Some(id) {
+            cache.set(Some(0));
+        }
+    }
+}
+
+impl Context {
+    pub fn state_stats(&self) -> StateStats {
+        self.state.stats.get()
+    }
+
+    pub fn reset_state_stats(&self) {
+        self.state.stats.set(StateStats::default());
+    }
+
+    pub(crate) fn bind_framebuffer(&self, id: GLuint) {
+        if self.state.read_framebuffer.get() == Some(id) {
+            self.bind_draw_framebuffer(id);
+        } else if self.state.draw_framebuffer.get() == Some(id) {
+            self.bind_read_framebuffer(id);
+        } else {
+            let s = &self.state;
+            s.set(&s.draw_framebuffer, id, || self.gl.bind_framebuffer(gl::FRAMEBUFFER, id));
+            s.read_framebuffer.set(Some(id));
+        }
+    }
+
+    pub(crate) fn bind_read_framebuffer(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.read_framebuffer, id, || self.gl.bind_framebuffer(gl::READ_FRAMEBUFFER, id));
+    }
+
+    pub(crate) fn bind_draw_framebuffer(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.draw_framebuffer, id, || self.gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, id));
+    }
+
+    pub(crate) fn bind_renderbuffer(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.renderbuffer, id, || self.gl.bind_renderbuffer(id));
+    }
+
+    pub(crate) fn bind_array_buffer(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.array_buffer, id, || self.gl.bind_buffer(gl::ARRAY_BUFFER, id));
+    }
+
+    /// Binds a buffer for uploading without touching vertex array state, unlike
+    /// `GL_ELEMENT_ARRAY_BUFFER`, whose binding belongs to the current VAO.
+    pub(crate) fn bind_copy_write_buffer(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.copy_write_buffer, id, || self.gl.bind_buffer(gl::COPY_WRITE_BUFFER, id));
+    }
+
+    pub(crate) fn bind_vertex_array(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.vertex_array, id, || self.gl.bind_vertex_array(id));
+    }
+
+    /// Binds a texture to unit 0, which is reserved for creating and modifying textures.
+    pub(crate) fn bind_texture_2d(&self, id: GLuint) {
+        self.bind_texture_unit(0, id);
+    }
+
+    pub(crate) fn bind_texture_unit(&self, unit: u32, id: GLuint) {
+        let s = &self.state;
+        assert!((unit as usize) < s.textures.len(), "Texture unit {} is out of range", unit);
+        let cache = &s.textures[unit as usize];
+        if cache.get() == Some(id) {
+            let mut stats = s.stats.get();
+            stats.skipped += 1;
+            s.stats.set(stats);
+            return;
+        }
+        s.set(&s.active_texture, unit, || self.gl.active_texture(unit));
+        s.set(cache, id, || self.gl.bind_texture(gl::TEXTURE_2D, id));
+    }
+
+    pub(crate) fn use_program(&self, id: GLuint) {
+        let s = &self.state;
+        s.set(&s.program, id, || self.gl.use_program(id));
+    }
+
+    pub(crate) fn viewport(&self, x: GLint, y: GLint, w: GLsizei, h: GLsizei) {
+        let s = &self.state;
+        s.set(&s.viewport, (x, y, w, h), || self.gl.viewport(x, y, w, h));
+    }
+
+    pub(crate) fn scissor(&self, x: GLint, y: GLint, w: GLsizei, h: GLsizei) {
+        let s = &self.state;
+        s.set(&s.scissor, (x, y, w, h), || self.gl.scissor(x, y, w, h));
+    }
+
+    pub(crate) fn set_scissor_test(&self, enabled: bool) {
+        let s = &self.state;
+        s.set(&s.scissor_test, enabled, || {
+            if enabled {
+                self.gl.enable(gl::SCISSOR_TEST);
+            } else {
+                self.gl.disable(gl::SCISSOR_TEST);
+            }
+        });
+    }
+
+    /// Sets which color attachment each fragment color number of `framebuffer` writes to.
+    pub(crate) fn set_draw_buffers(&self, framebuffer: GLuint, buffers: &[GLenum]) {
+        let s = &self.state;
+        let cached = s.draw_buffers.borrow().get(&framebuffer).map_or(false, |b| b[..] == *buffers);
+        if !cached {
+            self.bind_draw_framebuffer(framebuffer);
+            if buffers.is_empty() {
+                self.gl.draw_buffer(gl::NONE);
+            } else {
+                self.gl.draw_buffers(buffers);
+            }
+            s.draw_buffers.borrow_mut().insert(framebuffer, buffers.to_vec());
+        }
+        let mut stats = s.stats.get();
+        if cached {
+            stats.skipped += 1;
+        } else {
+            stats.issued += 1;
+        }
+        s.stats.set(stats);
+    }
+
+    fn active_query(&self, target: GLenum) -> &Cell<Option<GLuint>> {
+        match target {
+            gl::TIME_ELAPSED => &self.state.queries[0],
+            gl::SAMPLES_PASSED => &self.state.queries[1],
+            gl::ANY_SAMPLES_PASSED => &self.state.queries[2],
+            _ => unreachable!()
+        }
+    }
+
+    pub(crate) fn begin_query(&self, target: GLenum, id: GLuint) {
+        let active = self.active_query(target);
+        assert!(active.get().is_none(), "Another query of the same kind is already active");
+        self.gl.begin_query(target, id);
+        active.set(Some(id));
+    }
+
+    pub(crate) fn end_query(&self, target: GLenum) {
+        self.gl.end_query(target);
+        self.active_query(target).set(None);
+    }
+
+    pub(crate) fn delete_framebuffer(&self, id: GLuint) {
+        GlState::forget(&self.state.read_framebuffer, id);
+        GlState::forget(&self.state.draw_framebuffer, id);
+        self.state.draw_buffers.borrow_mut().remove(&id);
+        self.untrack(ResourceKind::Framebuffer, id);
+        self.gl.delete_framebuffer(id);
+    }
+
+    pub(crate) fn delete_renderbuffer(&self, id: GLuint) {
+        GlState::forget(&self.state.renderbuffer, id);
+        self.untrack(ResourceKind::Renderbuffer, id);
+        self.gl.delete_renderbuffer(id);
+    }
+
+    pub(crate) fn delete_vertex_array(&self, id: GLuint) {
+        GlState::forget(&self.state.vertex_array, id);
+        self.gl.delete_vertex_array(id);
+    }
+
+    pub(crate) fn delete_texture(&self, id: GLuint) {
+        for cache in &self.state.textures {
+            GlState::forget(cache, id);
+        }
+        self.untrack(ResourceKind::Texture, id);
+        self.gl.delete_texture(id);
+    }
+
+    pub(crate) fn delete_program(&self, id: GLuint) {
+        GlState::forget(&self.state.program, id);
+        self.untrack(ResourceKind::Program, id);
+        self.gl.delete_program(id);
+    }
+
+    pub(crate) fn delete_buffer(&self, id: GLuint) {
+        GlState::forget(&self.state.array_buffer, id);
+        GlState::forget(&self.state.copy_write_buffer, id);
+        self.forget_vertex_arrays_using(id);
+        self.untrack(ResourceKind::Buffer, id);
+        self.gl.delete_buffer(id);
+    }
+
+    pub(crate) fn delete_query(&self, id: GLuint) {
+        self.untrack(ResourceKind::Query, id);
+        self.gl.delete_query(id);
+    }
+}
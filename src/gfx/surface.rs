@@ -2,115 +2,221 @@ use std::marker::PhantomData;
 
 use gl::types::*;
 use gl;
+use image::RgbaImage;
 
-use gfx::Context;
+use gfx::{ Context, TextureFilter, ResourceKind, CapturedTarget };
 use gfx::Framebuffer;
+use gfx::texture::{ color_format_components, format_component_type, format_name };
+use gfx::shader::{ ComponentType, ProgramOutput };
+
+/// A color attachment as seen by the surfaces drawing to it.
+pub(crate) struct ColorTarget {
+    pub format: GLenum,
+    pub name: Option<String>
+}
+
+/// The window system picks the format of the default framebuffer, so only the component type of
+/// outputs drawn to the screen is checked.
+const SCREEN_TARGETS: &'static [ColorTarget] = &[ColorTarget { format: gl::RGBA8, name: None }];
+
+bitflags! {
+    pub struct BlitMask: GLbitfield {
+        const COLOR = gl::COLOR_BUFFER_BIT;
+        const DEPTH = gl::DEPTH_BUFFER_BIT;
+        const STENCIL = gl::STENCIL_BUFFER_BIT;
+    }
+}
 
 pub struct Surface<'a: 'b, 'b> {
-    ctx: &'a Context,
+    pub(crate) ctx: &'a Context,
     from: PhantomData<&'b mut Framebuffer<'a>>,
-    id: GLuint,
-    x: u32,
-    y: u32,
+    pub(crate) id: GLuint,
+    targets: &'b [ColorTarget],
+    samples: u32,
+    has_depth: bool,
+    pub(crate) x: u32,
+    pub(crate) y: u32,
     pub(crate) width: u32,
-    pub(crate) height: u32
+    pub(crate) height: u32,
+    pub(crate) framebuffer_height: u32,
+    viewport: (i32, i32, u32, u32),
+    base_clip: Rect,
+    clips: Vec<Rect>
+}
+
+/// An `(x, y, width, height)` rectangle in framebuffer coordinates.
+type Rect = (u32, u32, u32, u32);
+
+fn intersect(a: Rect, b: Rect) -> Rect {
+    let x0 = a.0.max(b.0);
+    let y0 = a.1.max(b.1);
+    let x1 = a.0.saturating_add(a.2).min(b.0.saturating_add(b.2)).max(x0);
+    let y1 = a.1.saturating_add(a.3).min(b.1.saturating_add(b.3)).max(y0);
+    (x0, y0, x1 - x0, y1 - y0)
 }
 
 impl<'a, 'b> Surface<'a, 'b> {
+    pub(crate) fn new(ctx: &'a Context, id: GLuint, targets: &'b [ColorTarget], samples: u32, has_depth: bool, width: u32, height: u32) -> Self {
+        Surface {
+            ctx: ctx,
+            from: PhantomData,
+            id: id,
+            targets: targets,
+            samples: samples,
+            has_depth: has_depth,
+            x: 0,
+            y: 0,
+            width: width,
+            height: height,
+            framebuffer_height: height,
+            viewport: (0, 0, width, height),
+            base_clip: (0, 0, width, height),
+            clips: Vec::new()
+        }
+    }
+
+    /// Restricts drawing and clearing to a rectangle relative to this surface, intersected with
+    /// the current clip rectangle. Undo with `pop_clip`.
+    pub fn push_clip(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        let clip = intersect(self.clip(), (self.x.saturating_add(x), self.y.saturating_add(y), width, height));
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        assert!(self.clips.pop().is_some(), "pop_clip called without a matching push_clip");
+    }
+
+    /// Returns the current clip rectangle relative to this surface.
+    pub fn get_clip(&self) -> (u32, u32, u32, u32) {
+        let (x, y, w, h) = self.clip();
+        (x - self.x, y - self.y, w, h)
+    }
+
+    /// Sets the area draw calls are mapped to, relative to this surface. The viewport may extend
+    /// past the edges of the surface, for example to scroll content; drawing is still clipped to
+    /// the surface. Defaults to the whole surface.
+    pub fn set_viewport(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.viewport = (x, y, width, height);
+    }
+
+    pub fn reset_viewport(&mut self) {
+        self.viewport = (0, 0, self.width, self.height);
+    }
+
+    fn clip(&self) -> Rect {
+        *self.clips.last().unwrap_or(&self.base_clip)
+    }
+
+    /// Clears the first color attachment.
     pub fn clear_color(&mut self, color: &[f32; 4]) {
+        self.clear_attachment(0, color);
+    }
+
+    /// Clears the color attachment at `index`, which must store floating point or normalized
+    /// values.
+    pub fn clear_attachment(&mut self, index: usize, color: &[f32; 4]) {
+        self.begin_clear_attachment(index, ComponentType::Float);
+        self.ctx.gl.clear_buffer_fv(gl::COLOR, index as GLint, color);
+        self.end_clear_attachment();
+    }
+
+    /// Clears the color attachment at `index`, which must store signed integers.
+    pub fn clear_attachment_int(&mut self, index: usize, color: &[i32; 4]) {
+        self.begin_clear_attachment(index, ComponentType::Int);
+        self.ctx.gl.clear_buffer_iv(gl::COLOR, index as GLint, color);
+        self.end_clear_attachment();
+    }
+
+    /// Clears the color attachment at `index`, which must store unsigned integers.
+    pub fn clear_attachment_uint(&mut self, index: usize, color: &[u32; 4]) {
+        self.begin_clear_attachment(index, ComponentType::UInt);
+        self.ctx.gl.clear_buffer_uiv(gl::COLOR, index as GLint, color);
+        self.end_clear_attachment();
+    }
+
+    fn begin_clear_attachment(&mut self, index: usize, component_type: ComponentType) {
+        assert!(!self.targets.is_empty(), "Cannot clear the color of a framebuffer without color attachments");
+        assert!(index < self.targets.len(), "Color attachment {} is out of bounds, there are {}", index, self.targets.len());
+        let format = self.targets[index].format;
+        assert!(
+            format_component_type(format) == component_type,
+            "Cannot clear color attachment {} ({}) with {:?} values", index, format_name(format), component_type
+        );
         self.bind();
-        unsafe {
-            gl::Scissor(self.x as GLint, self.y as GLint, self.width as GLint, self.height as GLint);
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::ClearBufferfv(gl::COLOR, 0, color as *const GLfloat);
-            gl::Disable(gl::SCISSOR_TEST);
+        if self.id != 0 {
+            // Drawing may have remapped the draw buffers, and clears address them by index.
+            let draw_buffers: Vec<GLenum> = (0..self.targets.len()).map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum).collect();
+            self.ctx.set_draw_buffers(self.id, &draw_buffers);
         }
+        self.enable_scissor();
+    }
+
+    fn end_clear_attachment(&mut self) {
+        self.ctx.set_scissor_test(false);
+        self.ctx.check_errors("Surface::clear_attachment");
     }
 
     pub fn clear_depth(&mut self, depth: f32) {
         self.bind();
-        unsafe {
-            gl::Scissor(self.x as GLint, self.y as GLint, self.width as GLint, self.height as GLint);
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::ClearBufferfv(gl::DEPTH, 0, &depth as *const GLfloat);
-            gl::Disable(gl::SCISSOR_TEST);
-        }
+        self.enable_scissor();
+        self.ctx.gl.clear_buffer_fv(gl::DEPTH, 0, &[depth]);
+        self.ctx.set_scissor_test(false);
+        self.ctx.check_errors("Surface::clear_depth");
     }
 
     pub fn clear_stencil(&mut self, stencil: i32) {
         self.bind();
-        unsafe {
-            gl::Scissor(self.x as GLint, self.y as GLint, self.width as GLint, self.height as GLint);
-            gl::Enable(gl::SCISSOR_TEST);
-            gl::ClearBufferiv(gl::STENCIL, 0, &stencil as *const GLint);
-            gl::Disable(gl::SCISSOR_TEST);
-        }
+        self.enable_scissor();
+        self.ctx.gl.clear_buffer_iv(gl::STENCIL, 0, &[stencil]);
+        self.ctx.set_scissor_test(false);
+        self.ctx.check_errors("Surface::clear_stencil");
     }
 
     pub fn subsurface<'c>(&'c mut self, x: u32, y: u32, width: u32, height: u32) -> Surface<'a, 'c> where 'b: 'c {
         assert!(x <= self.width, "x is out of bounds: {}", x);
         assert!(y <= self.height, "y is out of bounds: {}", y);
-        assert!(x + width <= self.width, "width is out of bounds: {}", width);
-        assert!(y + height <= self.height, "height is out of bounds: {}", height);
-        Surface {
-            ctx: self.ctx,
-            from: PhantomData,
-            id: self.id,
-            x: self.x + x,
-            y: self.y + y,
-            width: width,
-            height: height
-        }
+        assert!(width <= self.width - x, "width is out of bounds: {}", width);
+        assert!(height <= self.height - y, "height is out of bounds: {}", height);
+        self.region(x, y, width, height)
     }
 
     pub fn split_vertical<'c>(&'c mut self, x: u32) -> (Surface<'a, 'c>, Surface<'a, 'c>) where 'b: 'c {
         assert!(x <= self.width, "x is out of bounds: {}", x);
         (
-            Surface {
-                ctx: self.ctx,
-                from: PhantomData,
-                id: self.id,
-                y: self.y,
-                height: self.height,
-                x: 0,
-                width: x
-            },
-            Surface {
-                ctx: self.ctx,
-                from: PhantomData,
-                id: self.id,
-                y: self.y,
-                height: self.height,
-                x: x,
-                width: self.width - x
-            }
+            self.region(0, 0, x, self.height),
+            self.region(x, 0, self.width - x, self.height)
         )
     }
 
-    pub fn split_horizontal<'c>(&'c mut self, y: u32) -> (Surface<'a, 'c>, Surface<'a, 'c>) {
-        assert!(y <= self.y, "y is out of bounds: {}", y);
+    pub fn split_horizontal<'c>(&'c mut self, y: u32) -> (Surface<'a, 'c>, Surface<'a, 'c>) where 'b: 'c {
+        assert!(y <= self.height, "y is out of bounds: {}", y);
         (
-            Self {
-                ctx: self.ctx,
-                from: self.from,
-                id: self.id,
-                x: self.x,
-                width: self.width,
-                y: 0,
-                height: y
-            },
-            Self {
-                ctx: self.ctx,
-                from: self.from,
-                id: self.id,
-                x: self.x,
-                width: self.width,
-                y: y,
-                height: self.height - y
-            }
+            self.region(0, 0, self.width, y),
+            self.region(0, y, self.width, self.height - y)
         )
     }
 
+    /// Creates a surface covering a region relative to this one without checking bounds or
+    /// borrowing. Callers must hold a mutable borrow of `self` for `'c`.
+    pub(crate) fn region<'c>(&self, x: u32, y: u32, width: u32, height: u32) -> Surface<'a, 'c> where 'b: 'c {
+        Surface {
+            ctx: self.ctx,
+            from: PhantomData,
+            id: self.id,
+            targets: self.targets,
+            samples: self.samples,
+            has_depth: self.has_depth,
+            x: self.x + x,
+            y: self.y + y,
+            width: width,
+            height: height,
+            framebuffer_height: self.framebuffer_height,
+            viewport: (0, 0, width, height),
+            base_clip: intersect(self.clip(), (self.x + x, self.y + y, width, height)),
+            clips: Vec::new()
+        }
+    }
+
     pub fn get_width(&self) -> u32 {
         self.width
     }
@@ -119,22 +225,279 @@ impl<'a, 'b> Surface<'a, 'b> {
         self.height
     }
 
+    /// Copies `src` into this surface, scaling it to fit. Only color can be copied with linear
+    /// filtering.
+    pub fn blit_from(&mut self, src: &Surface, mask: BlitMask, filter: TextureFilter) {
+        self.blit_from_flipped(src, mask, filter, false, false);
+    }
+
+    pub fn blit_from_flipped(&mut self, src: &Surface, mask: BlitMask, filter: TextureFilter, flip_x: bool, flip_y: bool) {
+        if let TextureFilter::Linear = filter {
+            assert!(
+                !mask.intersects(BlitMask::DEPTH | BlitMask::STENCIL),
+                "Depth and stencil can only be blitted with nearest filtering"
+            );
+        }
+        let (mut x0, mut x1) = (self.x as GLint, (self.x + self.width) as GLint);
+        let (mut y0, mut y1) = (self.y as GLint, (self.y + self.height) as GLint);
+        if flip_x {
+            ::std::mem::swap(&mut x0, &mut x1);
+        }
+        if flip_y {
+            ::std::mem::swap(&mut y0, &mut y1);
+        }
+        self.ctx.bind_read_framebuffer(src.id);
+        self.ctx.bind_draw_framebuffer(self.id);
+        self.ctx.gl.blit_framebuffer(
+            [src.x as GLint, src.y as GLint, (src.x + src.width) as GLint, (src.y + src.height) as GLint],
+            [x0, y0, x1, y1],
+            mask.bits(), filter as GLenum
+        );
+        self.ctx.bind_framebuffer(self.id);
+        self.ctx.check_errors("Surface::blit_from");
+    }
+
+    /// Reads back the first color attachment. Rows are returned top to bottom. Multisampled
+    /// framebuffers must be resolved with `blit_from` first.
+    pub fn read_pixels(&self) -> RgbaImage {
+        assert!(!self.targets.is_empty(), "Cannot read the pixels of a framebuffer without color attachments");
+        let format = self.targets[0].format;
+        assert!(
+            format_component_type(format) == ComponentType::Float,
+            "Cannot read {} color attachments as RGBA pixels", format_name(format)
+        );
+        self.assert_single_sampled("read_pixels");
+        let mut data = vec![0u8; self.width as usize * self.height as usize * 4];
+        self.read(gl::RGBA, gl::UNSIGNED_BYTE, &mut data);
+        flip_rows(&mut data, self.width as usize * 4);
+        RgbaImage::from_raw(self.width, self.height, data).unwrap()
+    }
+
+    /// Reads back the depth buffer. Rows are returned top to bottom. Multisampled framebuffers must
+    /// be resolved with `blit_from` first. The window system may not give the screen a depth
+    /// buffer, so only framebuffers with a depth attachment can be read.
+    pub fn read_depth(&self) -> Vec<f32> {
+        assert!(self.has_depth, "read_depth needs a framebuffer with a depth attachment");
+        self.assert_single_sampled("read_depth");
+        let mut data = vec![0.0; self.width as usize * self.height as usize];
+        self.read(gl::DEPTH_COMPONENT, gl::FLOAT, as_bytes_mut(&mut data));
+        flip_rows(&mut data, self.width as usize);
+        data
+    }
+
+    /// Whether `read_pixels` can be called without resolving the framebuffer first.
+    pub(crate) fn can_read_pixels(&self) -> bool {
+        self.samples == 0 && self.targets.first().map_or(false, |t| format_component_type(t.format) == ComponentType::Float)
+    }
+
+    fn assert_single_sampled(&self, function: &str) {
+        assert!(
+            self.samples == 0,
+            "{} was called on a framebuffer with {} samples; blit it to a single-sampled one first", function, self.samples
+        );
+    }
+
+    fn read(&self, format: GLenum, gltype: GLenum, into: &mut [u8]) {
+        self.ctx.bind_framebuffer(self.id);
+        self.ctx.gl.read_pixels(self.x as GLint, self.y as GLint, self.width as GLint, self.height as GLint, format, gltype, into);
+        self.ctx.check_errors("Surface::read");
+    }
+
+    /// Checks each fragment output against the color attachment it will be written to and sets
+    /// the draw buffers accordingly. Outputs go to the attachment with the same name if the
+    /// attachments are named, and to the attachment with the same index otherwise.
+    pub(crate) fn bind_fragment_outputs(&self, outputs: &[ProgramOutput]) {
+        let named = self.targets.iter().any(|t| t.name.is_some());
+        let mut draw_buffers = Vec::with_capacity(outputs.len());
+        for (i, output) in outputs.iter().enumerate() {
+            let index = if named {
+                match self.targets.iter().position(|t| t.name.as_ref() == Some(&output.name)) {
+                    Some(index) => index,
+                    None => panic!("Fragment output '{}' has no color attachment with the same name", output.name)
+                }
+            } else {
+                assert!(i < self.targets.len(), "Fragment output '{}' ({}) has no color attachment to write to", output.name, i);
+                i
+            };
+            let format = self.targets[index].format;
+            let component_type = format_component_type(format);
+            assert!(
+                output.component_type == component_type,
+                "Fragment output '{}' is of type {:?}, but color attachment {} ({}) stores {:?} values",
+                output.name, output.component_type, index, format_name(format), component_type
+            );
+            if self.id != 0 {
+                let needed = color_format_components(format);
+                assert!(
+                    output.components >= needed,
+                    "Fragment output '{}' has {} components, but color attachment {} ({}) needs {}",
+                    output.name, output.components, index, format_name(format), needed
+                );
+            }
+            draw_buffers.push(gl::COLOR_ATTACHMENT0 + index as GLenum);
+        }
+        if self.id != 0 {
+            self.ctx.set_draw_buffers(self.id, &draw_buffers);
+        }
+    }
+
+    pub(crate) fn capture_target(&self) -> CapturedTarget {
+        let (x, y, w, h) = self.viewport;
+        CapturedTarget {
+            framebuffer: self.ctx.object_name(ResourceKind::Framebuffer, self.id),
+            rect: (self.x, self.y, self.width, self.height),
+            viewport: (self.x as i32 + x, self.y as i32 + y, w, h),
+            clip: self.clip()
+        }
+    }
+
     pub(crate) fn bind(&mut self) {
         self.ctx.bind_framebuffer(self.id);
-        unsafe { gl::Viewport(self.x as GLint, self.y as GLint, self.width as GLint, self.height as GLint) };
+        let (x, y, w, h) = self.viewport;
+        self.ctx.viewport(self.x as GLint + x, self.y as GLint + y, w as GLint, h as GLint);
+    }
+
+    /// Enables the scissor test with the current clip rectangle. Callers disable it again when
+    /// they are done so it doesn't affect blits and other surfaces.
+    pub(crate) fn enable_scissor(&self) {
+        let (x, y, w, h) = self.clip();
+        self.ctx.scissor(x as GLint, y as GLint, w as GLint, h as GLint);
+        self.ctx.set_scissor_test(true);
+    }
+}
+
+fn as_bytes_mut(data: &mut [f32]) -> &mut [u8] {
+    unsafe { ::std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, data.len() * 4) }
+}
+
+fn flip_rows<T>(data: &mut [T], row_length: usize) {
+    if row_length == 0 {
+        return;
+    }
+    let rows = data.len() / row_length;
+    for y in 0..rows / 2 {
+        let (top, bottom) = data.split_at_mut((rows - 1 - y) * row_length);
+        top[y * row_length..(y + 1) * row_length].swap_with_slice(&mut bottom[..row_length]);
     }
 }
 
 impl Context {
     pub(crate) fn create_screen_surface(&self, w: u32, h: u32) -> Surface {
-        Surface {
-            id: 0,
-            ctx: self,
-            from: PhantomData,
-            x: 0,
-            y: 0,
-            width: w,
-            height: h
+        Surface::new(self, 0, SCREEN_TARGETS, 0, false, w, h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ flip_rows, intersect };
+    use gfx::{ Context, TextureFormat, DepthStencilFormat };
+    use gfx::backend::{ RecordingBackend, Call, Arg };
+
+    #[test]
+    fn flip_rows_reverses_row_order() {
+        let mut data = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        flip_rows(&mut data, 3);
+        assert_eq!(data, [7, 8, 9, 4, 5, 6, 1, 2, 3]);
+
+        let mut data = [1, 2, 3, 4, 5, 6, 7, 8];
+        flip_rows(&mut data, 2);
+        assert_eq!(data, [7, 8, 5, 6, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn flip_rows_handles_degenerate_sizes() {
+        let mut single = [1, 2, 3];
+        flip_rows(&mut single, 3);
+        assert_eq!(single, [1, 2, 3]);
+
+        let mut empty: [u8; 0] = [];
+        flip_rows(&mut empty, 0);
+        flip_rows(&mut empty, 4);
+    }
+
+    #[test]
+    fn attachments_are_cleared_by_index_with_their_own_type() {
+        let rec = RecordingBackend::new();
+        let ctx = Context::with_backend(Box::new(rec.clone()));
+        let mut fb = ctx.create_framebuffer(vec![
+            ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba8).into(),
+            ctx.create_empty_texture_2d(8, 8, TextureFormat::R32UI).into(),
+            ctx.create_empty_texture_2d(8, 8, TextureFormat::Rgba32I).into()
+        ], None);
+        rec.clear_calls();
+        {
+            let mut surface = fb.surface();
+            surface.clear_color(&[0.5, 0.0, 0.0, 1.0]);
+            surface.clear_attachment_uint(1, &[7, 0, 0, 0]);
+            surface.clear_attachment_int(2, &[-1, 0, 0, 0]);
         }
+        let clear = |name: &'static str, index: i64, value: Arg| Call {
+            name: name,
+            args: vec![Arg::Int(::gl::COLOR as i64), Arg::Int(index), value]
+        };
+        assert_eq!(rec.calls_to("ClearBufferfv"), vec![clear("ClearBufferfv", 0, Arg::Floats(vec![0.5, 0.0, 0.0, 1.0]))]);
+        assert_eq!(rec.calls_to("ClearBufferuiv"), vec![clear("ClearBufferuiv", 1, Arg::Ints(vec![7, 0, 0, 0]))]);
+        assert_eq!(rec.calls_to("ClearBufferiv"), vec![clear("ClearBufferiv", 2, Arg::Ints(vec![-1, 0, 0, 0]))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot clear color attachment 0 (R32UI) with Float values")]
+    fn clearing_an_integer_attachment_with_floats_panics() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut fb = ctx.create_framebuffer(vec![ctx.create_empty_texture_2d(8, 8, TextureFormat::R32UI).into()], None);
+        fb.surface().clear_color(&[0.0; 4]);
+    }
+
+    #[test]
+    fn depth_is_read_from_depth_attachments() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut fb = ctx.create_framebuffer(
+            vec![ctx.create_empty_texture_2d(4, 2, TextureFormat::Rgba8).into()],
+            Some(ctx.create_renderbuffer(4, 2, DepthStencilFormat::Depth24Stencil8).into())
+        );
+        assert_eq!(fb.surface().read_depth().len(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "read_depth needs a framebuffer with a depth attachment")]
+    fn reading_depth_without_a_depth_attachment_panics() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut fb = ctx.create_framebuffer(
+            vec![ctx.create_empty_texture_2d(4, 2, TextureFormat::Rgba8).into()],
+            Some(ctx.create_renderbuffer(4, 2, DepthStencilFormat::Stencil8).into())
+        );
+        fb.surface().read_depth();
+    }
+
+    #[test]
+    #[should_panic(expected = "read_depth needs a framebuffer with a depth attachment")]
+    fn reading_the_screen_depth_panics() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        ctx.create_screen_surface(4, 2).read_depth();
+    }
+
+    #[test]
+    fn intersect_saturates_huge_rectangles() {
+        assert_eq!(intersect((0, 0, 8, 8), (4, 4, u32::max_value(), u32::max_value())), (4, 4, 4, 4));
+        assert_eq!(intersect((u32::max_value() - 1, 0, 8, 8), (0, 0, u32::max_value(), 8)), (u32::max_value() - 1, 0, 1, 8));
+    }
+
+    #[test]
+    fn huge_clips_are_limited_to_the_surface() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut screen = ctx.create_screen_surface(8, 8);
+        let mut sub = screen.subsurface(2, 2, 4, 4);
+        sub.push_clip(1, 1, u32::max_value(), u32::max_value());
+        assert_eq!(sub.get_clip(), (1, 1, 3, 3));
+        sub.push_clip(u32::max_value(), 0, 1, 1);
+        let (_, _, w, h) = sub.get_clip();
+        assert_eq!((w, h), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "width is out of bounds")]
+    fn subsurface_widths_that_overflow_panic() {
+        let ctx = Context::with_backend(Box::new(RecordingBackend::new()));
+        ctx.create_screen_surface(8, 8).subsurface(1, 0, u32::max_value(), 1);
     }
 }
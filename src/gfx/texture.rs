@@ -1,80 +1,117 @@
-use std::ptr;
-
 use gl::types::*;
 use gl;
 use image::{ ImageLuma8, ImageLumaA8, ImageRgb8, ImageRgba8, DynamicImage, GenericImage };
 
-use gfx::Context;
+use gfx::{ Context, ResourceKind, format_size };
+use gfx::shader::ComponentType;
 
 pub struct Texture2D<'a> {
     ctx: &'a Context,
     pub(crate) id: GLuint,
+    pub(crate) format: GLenum,
     width: u32,
     height: u32
 }
 
 impl<'a> Drop for Texture2D<'a> {
     fn drop(&mut self) {
-        unsafe { gl::DeleteTextures(1, &self.id); }
+        self.ctx.delete_texture(self.id);
     }
 }
 
 impl<'a> Texture2D<'a> {
     pub(crate) fn allocate(&mut self, w: u32, h: u32, format: GLenum) {
-        unsafe {
-            self.bind();
-            gl::TexImage2D(gl::TEXTURE_2D, 0, format as GLint, w as GLint, h as GLint, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
-            self.width = w;
-            self.height = h;
-        }
+        self.check_size(w, h);
+        let (pixel_format, pixel_type) = match format {
+            gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+            gl::DEPTH24_STENCIL8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+            gl::DEPTH32F_STENCIL8 => (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV),
+            gl::STENCIL_INDEX8 => (gl::STENCIL_INDEX, gl::UNSIGNED_BYTE),
+            _ => match format_component_type(format) {
+                ComponentType::Float => (gl::RGBA, gl::UNSIGNED_BYTE),
+                ComponentType::Int => (gl::RGBA_INTEGER, gl::INT),
+                ComponentType::UInt => (gl::RGBA_INTEGER, gl::UNSIGNED_INT)
+            }
+        };
+        self.bind();
+        self.ctx.gl.tex_image_2d(format, w as GLint, h as GLint, pixel_format, pixel_type, None);
+        self.format = format;
+        self.width = w;
+        self.height = h;
+        self.ctx.set_tracked_size(ResourceKind::Texture, self.id, w as usize * h as usize * format_size(format));
+        self.ctx.check_errors("Texture2D::allocate");
     }
 
     fn allocate_data(&mut self, data: &DynamicImage) {
+        self.check_size(data.width(), data.height());
         self.bind();
+        let gl = &self.ctx.gl;
         match data {
-            ImageLuma8(img) => unsafe {
-                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::R8 as GLint, img.width() as GLint, img.height() as GLint, 0, gl::RED, gl::UNSIGNED_BYTE, img.as_ptr() as *const GLvoid);
-            }
-            ImageLumaA8(img) => unsafe {
-                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RG8 as GLint, img.width() as GLint, img.height() as GLint, 0, gl::RG, gl::UNSIGNED_BYTE, img.as_ptr() as *const GLvoid);
-            }
-            ImageRgb8(img) => unsafe {
-                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGB8 as GLint, img.width() as GLint, img.height() as GLint, 0, gl::RGB, gl::UNSIGNED_BYTE, img.as_ptr() as *const GLvoid);
-            }
-            ImageRgba8(img) => unsafe {
-                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, img.width() as GLint, img.height() as GLint, 0, gl::RGBA, gl::UNSIGNED_BYTE, img.as_ptr() as *const GLvoid);
-            }
+            ImageLuma8(img) =>
+                gl.tex_image_2d(gl::R8, img.width() as GLint, img.height() as GLint, gl::RED, gl::UNSIGNED_BYTE, Some(&**img)),
+            ImageLumaA8(img) =>
+                gl.tex_image_2d(gl::RG8, img.width() as GLint, img.height() as GLint, gl::RG, gl::UNSIGNED_BYTE, Some(&**img)),
+            ImageRgb8(img) =>
+                gl.tex_image_2d(gl::RGB8, img.width() as GLint, img.height() as GLint, gl::RGB, gl::UNSIGNED_BYTE, Some(&**img)),
+            ImageRgba8(img) =>
+                gl.tex_image_2d(gl::RGBA8, img.width() as GLint, img.height() as GLint, gl::RGBA, gl::UNSIGNED_BYTE, Some(&**img))
         }
+        self.format = match data {
+            ImageLuma8(_) => gl::R8,
+            ImageLumaA8(_) => gl::RG8,
+            ImageRgb8(_) => gl::RGB8,
+            ImageRgba8(_) => gl::RGBA8
+        };
         self.width = data.width();
         self.height = data.height();
+        self.ctx.set_tracked_size(ResourceKind::Texture, self.id, self.width as usize * self.height as usize * format_size(self.format));
+        self.ctx.check_errors("Texture2D::allocate_data");
+    }
+
+    fn check_size(&self, w: u32, h: u32) {
+        let max = self.ctx.capabilities().max_texture_size;
+        assert!(w <= max && h <= max, "A {}x{} texture exceeds the maximum texture size of {}", w, h, max);
     }
 
     pub(crate) fn bind(&mut self) {
         self.ctx.bind_texture_2d(self.id);
     }
 
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn set_label(&mut self, label: &str) {
+        self.ctx.label_object(gl::TEXTURE, self.id, label);
+    }
+
     pub fn set_minify_filter(&mut self, filter: TextureFilter) {
         self.bind();
-        unsafe { gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as GLint) };
+        self.ctx.gl.tex_parameter_i(gl::TEXTURE_MIN_FILTER, filter as GLint);
+        self.ctx.check_errors("Texture2D::set_minify_filter");
     }
 
     pub fn set_magnify_filter(&mut self, filter: TextureFilter) {
         self.bind();
-        unsafe { gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as GLint) };
+        self.ctx.gl.tex_parameter_i(gl::TEXTURE_MAG_FILTER, filter as GLint);
+        self.ctx.check_errors("Texture2D::set_magnify_filter");
     }
 }
 
 impl Context {
     pub(crate) fn create_raw_texture_2d(&self) -> Texture2D {
-        let mut id = 0;
-        unsafe {
-            gl::GenTextures(1, &mut id);
-            self.bind_texture_2d(id);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, 0);
-        }
+        let id = self.gl.gen_texture();
+        self.track(ResourceKind::Texture, id);
+        self.bind_texture_2d(id);
+        self.gl.tex_parameter_i(gl::TEXTURE_MAX_LEVEL, 0);
         Texture2D {
             ctx: self,
             id: id,
+            format: 0,
             width: 0,
             height: 0
         }
@@ -85,9 +122,136 @@ impl Context {
         tex.allocate_data(from);
         tex
     }
+
+    pub fn create_empty_texture_2d(&self, width: u32, height: u32, format: TextureFormat) -> Texture2D {
+        let mut tex = self.create_raw_texture_2d();
+        tex.allocate(width, height, format as GLenum);
+        tex
+    }
+
+    /// `Stencil8` textures need OpenGL 4.4; use a `Stencil8` renderbuffer otherwise.
+    pub fn create_depth_texture_2d(&self, width: u32, height: u32, format: DepthStencilFormat) -> Texture2D {
+        assert!(
+            format != DepthStencilFormat::Stencil8 || self.capabilities().supports_version(4, 4),
+            "Stencil8 textures need OpenGL 4.4; use a Stencil8 renderbuffer instead"
+        );
+        let mut tex = self.create_raw_texture_2d();
+        tex.allocate(width, height, format as GLenum);
+        tex
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TextureFormat {
+    R8 = gl::R8,
+    Rg8 = gl::RG8,
+    Rgb8 = gl::RGB8,
+    Rgba8 = gl::RGBA8,
+    Srgb8Alpha8 = gl::SRGB8_ALPHA8,
+    R16F = gl::R16F,
+    Rg16F = gl::RG16F,
+    Rgba16F = gl::RGBA16F,
+    R32F = gl::R32F,
+    Rg32F = gl::RG32F,
+    Rgba32F = gl::RGBA32F,
+    R11FG11FB10F = gl::R11F_G11F_B10F,
+    R32I = gl::R32I,
+    R32UI = gl::R32UI,
+    Rgba8UI = gl::RGBA8UI,
+    Rgba32I = gl::RGBA32I,
+    Rgba32UI = gl::RGBA32UI
+}
+
+impl TextureFormat {
+    pub(crate) fn from_gl(format: GLenum) -> Option<Self> {
+        match format {
+            gl::R8 => Some(TextureFormat::R8),
+            gl::RG8 => Some(TextureFormat::Rg8),
+            gl::RGB8 => Some(TextureFormat::Rgb8),
+            gl::RGBA8 => Some(TextureFormat::Rgba8),
+            gl::SRGB8_ALPHA8 => Some(TextureFormat::Srgb8Alpha8),
+            gl::R16F => Some(TextureFormat::R16F),
+            gl::RG16F => Some(TextureFormat::Rg16F),
+            gl::RGBA16F => Some(TextureFormat::Rgba16F),
+            gl::R32F => Some(TextureFormat::R32F),
+            gl::RG32F => Some(TextureFormat::Rg32F),
+            gl::RGBA32F => Some(TextureFormat::Rgba32F),
+            gl::R11F_G11F_B10F => Some(TextureFormat::R11FG11FB10F),
+            gl::R32I => Some(TextureFormat::R32I),
+            gl::R32UI => Some(TextureFormat::R32UI),
+            gl::RGBA8UI => Some(TextureFormat::Rgba8UI),
+            gl::RGBA32I => Some(TextureFormat::Rgba32I),
+            gl::RGBA32UI => Some(TextureFormat::Rgba32UI),
+            _ => None
+        }
+    }
+}
+
+/// A readable name for a texture or renderbuffer internal format, for error messages.
+pub(crate) fn format_name(format: GLenum) -> String {
+    match (TextureFormat::from_gl(format), DepthStencilFormat::from_gl(format)) {
+        (Some(f), _) => format!("{:?}", f),
+        (_, Some(f)) => format!("{:?}", f),
+        _ => format!("0x{:X}", format)
+    }
+}
+
+pub(crate) fn format_component_type(format: GLenum) -> ComponentType {
+    match format {
+        gl::R32I | gl::RGBA32I => ComponentType::Int,
+        gl::R32UI | gl::RGBA8UI | gl::RGBA32UI => ComponentType::UInt,
+        _ => ComponentType::Float
+    }
+}
+
+pub(crate) fn color_format_components(format: GLenum) -> u32 {
+    match format {
+        gl::R8 | gl::R16F | gl::R32F | gl::R32I | gl::R32UI => 1,
+        gl::RG8 | gl::RG16F | gl::RG32F => 2,
+        gl::RGB8 | gl::R11F_G11F_B10F => 3,
+        _ => 4
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DepthStencilFormat {
+    Depth16 = gl::DEPTH_COMPONENT16,
+    Depth24 = gl::DEPTH_COMPONENT24,
+    Depth32F = gl::DEPTH_COMPONENT32F,
+    Depth24Stencil8 = gl::DEPTH24_STENCIL8,
+    Depth32FStencil8 = gl::DEPTH32F_STENCIL8,
+    Stencil8 = gl::STENCIL_INDEX8
+}
+
+impl DepthStencilFormat {
+    pub(crate) fn from_gl(format: GLenum) -> Option<Self> {
+        match format {
+            gl::DEPTH_COMPONENT16 => Some(DepthStencilFormat::Depth16),
+            gl::DEPTH_COMPONENT24 => Some(DepthStencilFormat::Depth24),
+            gl::DEPTH_COMPONENT32F => Some(DepthStencilFormat::Depth32F),
+            gl::DEPTH24_STENCIL8 => Some(DepthStencilFormat::Depth24Stencil8),
+            gl::DEPTH32F_STENCIL8 => Some(DepthStencilFormat::Depth32FStencil8),
+            gl::STENCIL_INDEX8 => Some(DepthStencilFormat::Stencil8),
+            _ => None
+        }
+    }
+
+    pub(crate) fn attachment_point(self) -> GLenum {
+        match self {
+            DepthStencilFormat::Depth16 |
+            DepthStencilFormat::Depth24 |
+            DepthStencilFormat::Depth32F => gl::DEPTH_ATTACHMENT,
+            DepthStencilFormat::Depth24Stencil8 |
+            DepthStencilFormat::Depth32FStencil8 => gl::DEPTH_STENCIL_ATTACHMENT,
+            DepthStencilFormat::Stencil8 => gl::STENCIL_ATTACHMENT
+        }
+    }
 }
 
 #[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum TextureFilter {
     Nearest = gl::NEAREST,
     Linear = gl::LINEAR
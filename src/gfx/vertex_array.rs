@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gl::types::*;
+use gl;
+
+use gfx::Context;
+
+/// The source of one vertex attribute, as passed to `glVertexAttribPointer`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct AttribPointerData {
+    pub buf_id: GLuint,
+    pub index: GLuint,
+    pub size: GLint,
+    pub gltype: GLenum,
+    pub normalized: bool,
+    pub stride: GLint,
+    pub offset: usize
+}
+
+/// Vertex array objects keyed by their index buffer (0 for none) and the complete set of attribute
+/// bindings they hold, so any program drawing from the same buffers with the same layout reuses
+/// one VAO.
+pub(crate) struct VertexArrayCache {
+    vaos: RefCell<HashMap<GLuint, HashMap<Vec<AttribPointerData>, GLuint>>>
+}
+
+impl VertexArrayCache {
+    pub fn new() -> Self {
+        VertexArrayCache {
+            vaos: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+impl Context {
+    /// Binds a VAO set up with `bindings` and `index_buffer`, creating it the first time they are
+    /// seen.
+    pub(crate) fn bind_vertex_array_for(&self, bindings: &[AttribPointerData], index_buffer: GLuint) {
+        let existing = self.vertex_arrays.vaos.borrow().get(&index_buffer).and_then(|v| v.get(bindings)).cloned();
+        match existing {
+            Some(vao) => self.bind_vertex_array(vao),
+            None => {
+                let vao = self.gl.gen_vertex_array();
+                self.bind_vertex_array(vao);
+                for binding in bindings {
+                    self.bind_array_buffer(binding.buf_id);
+                    self.gl.vertex_attrib_pointer(
+                        binding.index, binding.size, binding.gltype, binding.normalized,
+                        binding.stride, binding.offset
+                    );
+                    self.gl.enable_vertex_attrib_array(binding.index);
+                }
+                if index_buffer != 0 {
+                    self.gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+                }
+                self.vertex_arrays.vaos.borrow_mut().entry(index_buffer).or_insert_with(HashMap::new).insert(bindings.to_vec(), vao);
+            }
+        }
+    }
+
+    /// Deletes every cached VAO that reads from `buf_id`, so a recycled buffer name can't be
+    /// drawn through a stale VAO.
+    pub(crate) fn forget_vertex_arrays_using(&self, buf_id: GLuint) {
+        let mut stale = Vec::new();
+        {
+            let mut vaos = self.vertex_arrays.vaos.borrow_mut();
+            if let Some(by_bindings) = vaos.remove(&buf_id) {
+                stale.extend(by_bindings.values().cloned());
+            }
+            for by_bindings in vaos.values_mut() {
+                by_bindings.retain(|bindings, &mut vao| {
+                    let keep = bindings.iter().all(|b| b.buf_id != buf_id);
+                    if !keep {
+                        stale.push(vao);
+                    }
+                    keep
+                });
+            }
+        }
+        for vao in stale {
+            self.delete_vertex_array(vao);
+        }
+    }
+
+    pub fn cached_vertex_arrays(&self) -> usize {
+        self.vertex_arrays.vaos.borrow().values().map(|v| v.len()).sum()
+    }
+
+    pub(crate) fn delete_vertex_arrays(&self) {
+        let vaos: Vec<GLuint> = self.vertex_arrays.vaos.borrow_mut().drain().flat_map(|(_, v)| v.into_iter().map(|(_, vao)| vao)).collect();
+        for vao in vaos {
+            self.delete_vertex_array(vao);
+        }
+    }
+}
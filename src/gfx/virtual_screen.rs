@@ -0,0 +1,213 @@
+use std::cell::Cell;
+
+use gfx::{ Context, Framebuffer, Surface, TextureFormat, TextureFilter, BlitMask };
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Scaling {
+    /// Scale by the largest whole number that fits, falling back to `AspectPreserving` if the
+    /// screen is smaller than the virtual resolution.
+    Integer,
+    AspectPreserving,
+    Stretch
+}
+
+/// A low resolution offscreen target that is scaled up to fill the screen, with bars filling any
+/// leftover space.
+pub struct VirtualScreen<'a> {
+    framebuffer: Framebuffer<'a>,
+    scaling: Scaling,
+    filter: TextureFilter,
+    bar_color: [f32; 4],
+    content_scale: (f32, f32),
+    layout: Cell<Option<Layout>>
+}
+
+/// Where the virtual screen was last presented, in framebuffer pixels with `y` measured from the
+/// bottom of a framebuffer `framebuffer_height` pixels high.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Layout {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    framebuffer_height: u32
+}
+
+impl<'a> VirtualScreen<'a> {
+    pub fn surface<'b>(&'b mut self) -> Surface<'a, 'b> {
+        self.framebuffer.surface()
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.framebuffer.get_width()
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.framebuffer.get_height()
+    }
+
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+    }
+
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = filter;
+    }
+
+    pub fn set_bar_color(&mut self, color: [f32; 4]) {
+        self.bar_color = color;
+    }
+
+    /// Sets the number of framebuffer pixels per window coordinate used by `to_virtual`. Pass on
+    /// the values from `Event::ContentScaleChange` on HiDPI displays. Defaults to 1.
+    pub fn set_content_scale(&mut self, x: f32, y: f32) {
+        self.content_scale = (x, y);
+    }
+
+    /// Scales the virtual screen onto `to` and fills the remaining area with the bar color.
+    pub fn present(&mut self, to: &mut Surface) {
+        let layout = compute_layout((self.get_width(), self.get_height()), to, self.scaling);
+        self.layout.set(Some(layout));
+        to.clear_color(&self.bar_color);
+        let mut dest = to.subsurface(layout.x - to.x, layout.y - to.y, layout.width, layout.height);
+        dest.blit_from(&self.framebuffer.surface(), BlitMask::COLOR, self.filter);
+    }
+
+    /// Maps a position in window coordinates (as in `Event::MousePos`) to a virtual pixel, using
+    /// the layout from the last call to `present`. Returns `None` if the position is outside the
+    /// scaled virtual screen.
+    pub fn to_virtual(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let layout = self.layout.get()?;
+        to_virtual(&layout, (self.get_width(), self.get_height()), self.content_scale, x, y)
+    }
+}
+
+fn compute_layout(virtual_size: (u32, u32), to: &Surface, scaling: Scaling) -> Layout {
+    let (x, y, width, height) = fit(virtual_size, (to.get_width(), to.get_height()), scaling);
+    Layout {
+        x: to.x + x,
+        y: to.y + y,
+        width: width,
+        height: height,
+        framebuffer_height: to.framebuffer_height
+    }
+}
+
+/// Centers the virtual screen within an area of the given size, returning `(x, y, width, height)`.
+fn fit((vw, vh): (u32, u32), (area_width, area_height): (u32, u32), scaling: Scaling) -> (u32, u32, u32, u32) {
+    let integer_scale = (area_width / vw).min(area_height / vh);
+    let (width, height) = match scaling {
+        Scaling::Integer if integer_scale >= 1 => (vw * integer_scale, vh * integer_scale),
+        Scaling::Stretch => (area_width, area_height),
+        _ => {
+            let scale = (area_width as f64 / vw as f64).min(area_height as f64 / vh as f64);
+            (
+                ((vw as f64 * scale).round() as u32).min(area_width),
+                ((vh as f64 * scale).round() as u32).min(area_height)
+            )
+        }
+    };
+    ((area_width - width) / 2, (area_height - height) / 2, width, height)
+}
+
+fn to_virtual(layout: &Layout, (vw, vh): (u32, u32), (sx, sy): (f32, f32), x: i32, y: i32) -> Option<(i32, i32)> {
+    let px = (x as f64 * sx as f64).floor() as i64;
+    let py = (y as f64 * sy as f64).floor() as i64;
+    let top = layout.framebuffer_height as i64 - layout.y as i64 - layout.height as i64;
+    let rx = px - layout.x as i64;
+    let ry = py - top;
+    if rx < 0 || ry < 0 || rx >= layout.width as i64 || ry >= layout.height as i64 {
+        return None;
+    }
+    Some((
+        (rx * vw as i64 / layout.width as i64) as i32,
+        (ry * vh as i64 / layout.height as i64) as i32
+    ))
+}
+
+impl Context {
+    pub fn create_virtual_screen(&self, width: u32, height: u32, scaling: Scaling) -> VirtualScreen {
+        assert!(width > 0 && height > 0, "A virtual screen must not be empty, but {}x{} was requested", width, height);
+        let color = self.create_empty_texture_2d(width, height, TextureFormat::Rgba8);
+        VirtualScreen {
+            framebuffer: self.create_framebuffer(vec![color.into()], None),
+            scaling: scaling,
+            filter: TextureFilter::Nearest,
+            bar_color: [0.0, 0.0, 0.0, 1.0],
+            content_scale: (1.0, 1.0),
+            layout: Cell::new(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ fit, to_virtual, Layout, Scaling };
+
+    #[test]
+    fn integer_scaling_uses_the_largest_whole_multiple() {
+        assert_eq!(fit((320, 180), (1280, 720), Scaling::Integer), (0, 0, 1280, 720));
+        assert_eq!(fit((320, 180), (1000, 700), Scaling::Integer), (20, 80, 960, 540));
+    }
+
+    #[test]
+    fn integer_scaling_falls_back_to_preserving_the_aspect_ratio() {
+        assert_eq!(fit((320, 180), (160, 160), Scaling::Integer), (0, 35, 160, 90));
+    }
+
+    #[test]
+    fn aspect_preserving_letterboxes_and_pillarboxes() {
+        assert_eq!(fit((320, 180), (640, 480), Scaling::AspectPreserving), (0, 60, 640, 360));
+        assert_eq!(fit((320, 180), (1000, 360), Scaling::AspectPreserving), (180, 0, 640, 360));
+    }
+
+    #[test]
+    fn stretch_fills_the_area() {
+        assert_eq!(fit((320, 180), (500, 500), Scaling::Stretch), (0, 0, 500, 500));
+    }
+
+    #[test]
+    fn an_empty_area_gives_an_empty_layout() {
+        for &scaling in &[Scaling::Integer, Scaling::AspectPreserving, Scaling::Stretch] {
+            assert_eq!(fit((320, 180), (0, 0), scaling), (0, 0, 0, 0));
+        }
+    }
+
+    fn layout(x: u32, y: u32, width: u32, height: u32, framebuffer_height: u32) -> Layout {
+        Layout { x: x, y: y, width: width, height: height, framebuffer_height: framebuffer_height }
+    }
+
+    #[test]
+    fn to_virtual_maps_window_coordinates_from_the_top() {
+        // 320x180 shown at 2x with 60 pixel bars above and below in a 640x480 window.
+        let l = layout(0, 60, 640, 360, 480);
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), 0, 60), Some((0, 0)));
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), 639, 419), Some((319, 179)));
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), 10, 59), None);
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), 10, 420), None);
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), -1, 100), None);
+    }
+
+    #[test]
+    fn to_virtual_accounts_for_the_target_rect() {
+        // Presented to the top right quarter of an 800x600 framebuffer.
+        let l = layout(400, 300, 400, 300, 600);
+        assert_eq!(to_virtual(&l, (100, 75), (1.0, 1.0), 400, 0), Some((0, 0)));
+        assert_eq!(to_virtual(&l, (100, 75), (1.0, 1.0), 799, 299), Some((99, 74)));
+        assert_eq!(to_virtual(&l, (100, 75), (1.0, 1.0), 399, 0), None);
+        assert_eq!(to_virtual(&l, (100, 75), (1.0, 1.0), 400, 300), None);
+    }
+
+    #[test]
+    fn to_virtual_applies_the_content_scale() {
+        let l = layout(0, 0, 1280, 720, 720);
+        assert_eq!(to_virtual(&l, (320, 180), (2.0, 2.0), 320, 180), Some((160, 90)));
+        assert_eq!(to_virtual(&l, (320, 180), (2.0, 2.0), 640, 100), None);
+    }
+
+    #[test]
+    fn to_virtual_rejects_everything_for_an_empty_layout() {
+        let l = layout(0, 0, 0, 0, 0);
+        assert_eq!(to_virtual(&l, (320, 180), (1.0, 1.0), 0, 0), None);
+    }
+}
@@ -7,6 +7,12 @@ extern crate image;
 extern crate enum_primitive;
 #[macro_use]
 extern crate bitflags;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
 
 use libc::{ c_void, c_int, c_char };
 
@@ -14,16 +20,42 @@ mod ffi;
 use ffi::*;
 
 mod events;
-pub use events::{ Event, MouseButton, Key, Modifiers };
+pub use events::{ Event, TimedEvent, MouseButton, Key, Modifiers, GamepadHandle, GamepadButton, GamepadAxis };
+
+mod simulation;
+pub use simulation::{ simulate, Simulation };
+
+mod threaded;
+pub use threaded::{ launch_threaded, Update, Render };
+
+mod split_screen;
+pub use split_screen::{ SplitScreen, InputDevice };
 
 pub mod gfx;
 
 pub trait Game {
     fn frame(&mut self, screen: gfx::Surface, delta: f64);
 
+    /// Called while the window is being resized or otherwise needs repainting between frames.
+    /// This must not advance the game. By default the last frame is stretched over the screen if
+    /// `Configuration::save_frames` is set, and the screen is cleared to black otherwise.
+    fn redraw(&mut self, mut screen: gfx::Surface) {
+        let ctx = screen.ctx;
+        if !ctx.present_saved_frame(&mut screen) {
+            screen.clear_color(&[0.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
     fn should_exit(&mut self) -> bool;
 
     fn event(&mut self, event: Event);
+
+    /// Receives every event along with the time it was received. Events are always delivered in
+    /// the order they were received, before the frame during which they occurred. By default the
+    /// time is dropped and the event is passed on to `event`.
+    fn timed_event(&mut self, event: TimedEvent) {
+        self.event(event.event);
+    }
 }
 
 pub fn launch<F>(config: Configuration, init: F)
@@ -33,6 +65,7 @@ where
     unsafe {
         init_glfw();
 
+        glfwWindowHint(GLFW_OPENGL_DEBUG_CONTEXT, if config.debug { 1 } else { 0 });
         let title = to_cstring(config.title);
         let window = match config.mode {
             WindowMode::Windowed { width, height, resizeable, min_limits, max_limits } => {
@@ -61,11 +94,7 @@ where
             glfwTerminate();
         });
 
-        glfwMakeContextCurrent(window);
-        gl::load_with(|s| {
-            let c = to_cstring(s);
-            glfwGetProcAddress(c.as_ptr())
-        });
+        make_current(window);
 
         setup_callbacks(window);
 
@@ -78,31 +107,32 @@ where
                 game: init(&ctx),
                 ctx: &ctx,
                 queue: Vec::new(),
+                gamepads: [None; GLFW_JOYSTICK_LAST as usize + 1],
                 polling: false,
                 width: w as u32,
                 height: h as u32
             }
         };
-        glfwSetWindowUserPointer(window, &mut target as *mut events::Target as *mut c_void);
+        glfwSetWindowUserPointer(window, &mut target as *mut events::Target<Game> as *mut c_void);
 
         let mut last_time = glfwGetTime();
         while !target.game.should_exit() {
-            for e in target.queue.drain(0..) {
-                if let Event::Resize(width, height) = e {
-                    target.width = width;
-                    target.height = height;
-                }
-                target.game.event(e);
-            }
             target.polling = true;
             glfwPollEvents();
             target.polling = false;
+            target.poll_gamepads();
+            target.dispatch_queued();
 
             let now = glfwGetTime();
             let delta = now - last_time;
             last_time = now;
+            target.ctx.begin_frame();
             target.game.frame(target.ctx.create_screen_surface(target.width, target.height), delta);
+            target.ctx.end_frame();
 
+            if config.save_frames {
+                target.ctx.save_frame(target.width, target.height);
+            }
             glfwSwapBuffers(window);
         }
 
@@ -113,11 +143,38 @@ where
     }
 }
 
+/// Window and context settings for `launch`. Fields added later have defaults, so prefer
+/// `Configuration { title: "Game", ..Default::default() }` over listing every field.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Configuration<'a> {
     pub mode: WindowMode,
-    pub title: &'a str
+    pub title: &'a str,
+    /// Requests an OpenGL debug context so `gfx::Context::set_debug_callback` receives messages.
+    pub debug: bool,
+    /// Copies every frame so the default `Game::redraw` can repaint it. This costs a full screen
+    /// blit per frame, so leave it off if `redraw` is overridden.
+    pub save_frames: bool
+}
+
+impl<'a> Default for Configuration<'a> {
+    /// A resizeable 1280x720 window without a debug context or saved frames.
+    fn default() -> Self {
+        Configuration {
+            mode: WindowMode::Windowed {
+                width: 1280,
+                height: 720,
+                resizeable: true,
+                min_limits: (None, None),
+                max_limits: (None, None)
+            },
+            title: "",
+            debug: false,
+            save_frames: false
+        }
+    }
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WindowMode {
     Windowed {
         width: i32,
@@ -143,6 +200,16 @@ fn init_glfw() {
     }
 }
 
+fn make_current(window: *mut GLFWwindow) {
+    unsafe {
+        glfwMakeContextCurrent(window);
+        gl::load_with(|s| {
+            let c = to_cstring(s);
+            glfwGetProcAddress(c.as_ptr())
+        });
+    }
+}
+
 fn setup_callbacks(window: *mut GLFWwindow) {
     unsafe {
         glfwSetWindowCloseCallback(window, Some(events::window_close));
@@ -0,0 +1,172 @@
+use ffi::*;
+use events::Target;
+use { Game, Event, ScopeGaurd, gfx, init_glfw, make_current };
+
+/// Drives a `Game` without a window producing input. Events sent to the simulation go through the
+/// same queue as real input and are delivered before the next frame.
+pub struct Simulation<'a, G: Game + 'a> {
+    target: Target<'a, G>,
+    time: f64
+}
+
+impl<'a, G: Game + 'a> Simulation<'a, G> {
+    pub fn new(ctx: &'a gfx::Context, game: G, width: u32, height: u32) -> Self {
+        Simulation {
+            target: Target {
+                game: Box::new(game),
+                ctx: ctx,
+                queue: Vec::new(),
+                gamepads: [None; GLFW_JOYSTICK_LAST as usize + 1],
+                polling: false,
+                width: width,
+                height: height
+            },
+            time: 0.0
+        }
+    }
+
+    pub fn send(&mut self, event: Event) {
+        self.target.push(event, self.time);
+    }
+
+    pub fn frame(&mut self, delta: f64) {
+        self.target.dispatch_queued();
+        self.time += delta;
+        let screen = self.target.ctx.create_screen_surface(self.target.width, self.target.height);
+        self.target.ctx.begin_frame();
+        self.target.game.frame(screen, delta);
+        self.target.ctx.end_frame();
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn game(&self) -> &G {
+        &self.target.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut G {
+        &mut self.target.game
+    }
+
+    pub fn into_game(self) -> G {
+        *self.target.game
+    }
+}
+
+/// Creates a hidden window to provide an OpenGL context for `f`. No window events are delivered;
+/// use a `Simulation` to feed input to a game.
+pub fn simulate<F, R>(f: F) -> R
+where
+    F: for<'a> FnOnce(&'a gfx::Context) -> R
+{
+    unsafe {
+        init_glfw();
+
+        glfwWindowHint(GLFW_VISIBLE, 0);
+        let window = glfwCreateWindow(1, 1, to_cstring("").as_ptr(), ::std::ptr::null_mut(), ::std::ptr::null_mut());
+        let _gaurd = ScopeGaurd(|| {
+            glfwDestroyWindow(window);
+            glfwTerminate();
+        });
+
+        make_current(window);
+
+        let ctx = gfx::Context::create();
+        f(&ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use { Game, Event, TimedEvent, MouseButton, Modifiers, gfx };
+    use gfx::backend::RecordingBackend;
+    use super::Simulation;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<TimedEvent>,
+        frames: Vec<(u32, u32, f64)>
+    }
+
+    impl Game for Recorder {
+        fn frame(&mut self, screen: gfx::Surface, delta: f64) {
+            self.frames.push((screen.get_width(), screen.get_height(), delta));
+        }
+
+        fn should_exit(&mut self) -> bool {
+            false
+        }
+
+        fn event(&mut self, _event: Event) {
+            unreachable!()
+        }
+
+        fn timed_event(&mut self, event: TimedEvent) {
+            self.events.push(event);
+        }
+    }
+
+    #[test]
+    fn events_are_delivered_in_order_before_the_next_frame() {
+        let ctx = gfx::Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut sim = Simulation::new(&ctx, Recorder::default(), 64, 48);
+        sim.send(Event::MousePos(3, 4));
+        sim.send(Event::MousePress(MouseButton::Left, Modifiers::empty()));
+        assert!(sim.game().events.is_empty());
+
+        sim.frame(0.25);
+        assert_eq!(sim.game().events, vec![
+            TimedEvent { time: 0.0, event: Event::MousePos(3, 4) },
+            TimedEvent { time: 0.0, event: Event::MousePress(MouseButton::Left, Modifiers::empty()) }
+        ]);
+        assert_eq!(sim.game().frames, vec![(64, 48, 0.25)]);
+
+        sim.send(Event::Resize(32, 16));
+        sim.frame(0.5);
+        assert_eq!(sim.game().events[2], TimedEvent { time: 0.25, event: Event::Resize(32, 16) });
+        assert_eq!(sim.game().frames[1], (32, 16, 0.5));
+        assert_eq!(sim.time(), 0.75);
+
+        sim.frame(0.125);
+        assert_eq!(sim.game().events.len(), 3, "Events should only be delivered once");
+        assert_eq!(sim.into_game().frames.len(), 3);
+    }
+
+    #[test]
+    fn frames_are_captured_like_windowed_frames() {
+        let ctx = gfx::Context::with_backend(Box::new(RecordingBackend::new()));
+        let mut sim = Simulation::new(&ctx, Recorder::default(), 8, 8);
+        ctx.capture_next_frame(false);
+        sim.frame(0.1);
+        assert!(ctx.take_capture().is_some());
+        sim.frame(0.1);
+        assert!(ctx.take_capture().is_none());
+    }
+
+    #[test]
+    fn default_redraw_draws_before_the_swap() {
+        let rec = RecordingBackend::new();
+        let ctx = gfx::Context::with_backend(Box::new(rec.clone()));
+        let mut game = Recorder::default();
+
+        game.redraw(ctx.create_screen_surface(8, 8));
+        assert_eq!(rec.calls_to("BlitFramebuffer").len(), 0);
+        assert_eq!(rec.calls_to("ClearBufferfv").len(), 1, "Nothing saved, so the screen should be cleared");
+
+        // A minimised window has nothing worth saving.
+        ctx.save_frame(0, 0);
+        assert_eq!(rec.live_objects("Framebuffer"), 0);
+
+        ctx.save_frame(8, 8);
+        rec.clear_calls();
+        game.redraw(ctx.create_screen_surface(16, 16));
+        assert_eq!(rec.calls_to("BlitFramebuffer").len(), 1);
+        assert_eq!(rec.calls_to("ClearBufferfv").len(), 0);
+
+        rec.clear_calls();
+        game.redraw(ctx.create_screen_surface(0, 0));
+        assert_eq!(rec.calls_to("BlitFramebuffer").len(), 0);
+    }
+}
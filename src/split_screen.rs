@@ -0,0 +1,315 @@
+use { Game, Event, TimedEvent, Key, GamepadHandle, gfx };
+use gfx::Size;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum InputDevice {
+    /// Every key, including text input.
+    Keyboard,
+    /// Only the listed keys, for sharing one keyboard between players. Text input is not routed.
+    Keys(Vec<Key>),
+    Mouse,
+    Gamepad(GamepadHandle)
+}
+
+struct Player<'a> {
+    game: Box<Game + 'a>,
+    devices: Vec<InputDevice>,
+    viewport: (u32, u32, u32, u32)
+}
+
+impl<'a> Player<'a> {
+    fn owns(&self, event: &Event) -> bool {
+        self.devices.iter().any(|device| match (device, event) {
+            (InputDevice::Keyboard, Event::KeyPress(..)) |
+            (InputDevice::Keyboard, Event::KeyRepeat(..)) |
+            (InputDevice::Keyboard, Event::KeyRelease(..)) |
+            (InputDevice::Keyboard, Event::Character(..)) => true,
+            (InputDevice::Keys(keys), Event::KeyPress(Some(k), ..)) |
+            (InputDevice::Keys(keys), Event::KeyRepeat(Some(k), ..)) |
+            (InputDevice::Keys(keys), Event::KeyRelease(Some(k), ..)) => keys.contains(k),
+            (InputDevice::Mouse, Event::MousePress(..)) |
+            (InputDevice::Mouse, Event::MouseRelease(..)) |
+            (InputDevice::Mouse, Event::MousePos(..)) |
+            (InputDevice::Mouse, Event::Scroll(..)) => true,
+            (InputDevice::Gamepad(g), Event::GamepadConnected(h)) |
+            (InputDevice::Gamepad(g), Event::GamepadDisconnected(h)) |
+            (InputDevice::Gamepad(g), Event::GamepadPress(h, _)) |
+            (InputDevice::Gamepad(g), Event::GamepadRelease(h, _)) |
+            (InputDevice::Gamepad(g), Event::GamepadAxis(h, _, _)) => g == h,
+            _ => false
+        })
+    }
+}
+
+/// Divides the screen between up to four players, each with its own `Game`. Input events are only
+/// delivered to the players owning the device that produced them, and each player sees its
+/// viewport as its whole screen.
+pub struct SplitScreen<'a> {
+    players: Vec<Player<'a>>,
+    width: u32,
+    height: u32,
+    content_scale: (f32, f32),
+    /// The time of the latest event, used for `Resize` events sent outside of event dispatch.
+    time: f64
+}
+
+impl<'a> SplitScreen<'a> {
+    pub fn new() -> Self {
+        SplitScreen {
+            players: Vec::new(),
+            width: 0,
+            height: 0,
+            content_scale: (1.0, 1.0),
+            time: 0.0
+        }
+    }
+
+    pub fn add_player(&mut self, game: Box<Game + 'a>, devices: Vec<InputDevice>) -> usize {
+        assert!(self.players.len() < 4, "Split screen supports at most 4 players");
+        self.players.push(Player {
+            game: game,
+            devices: devices,
+            viewport: (0, 0, 0, 0)
+        });
+        let (width, height, time) = (self.width, self.height, self.time);
+        self.relayout(width, height, time);
+        self.players.len() - 1
+    }
+
+    pub fn player(&mut self, index: usize) -> &mut (Game + 'a) {
+        &mut *self.players[index].game
+    }
+
+    pub fn devices(&mut self, index: usize) -> &mut Vec<InputDevice> {
+        &mut self.players[index].devices
+    }
+
+    /// Sets the number of framebuffer pixels per window coordinate, used to map mouse positions
+    /// into viewports. This is updated by `Event::ContentScaleChange`, and defaults to 1.
+    pub fn set_content_scale(&mut self, x: f32, y: f32) {
+        self.content_scale = (x, y);
+    }
+
+    fn relayout(&mut self, width: u32, height: u32, time: f64) {
+        self.width = width;
+        self.height = height;
+        let viewports = viewports(width, height, self.players.len());
+        for (player, viewport) in self.players.iter_mut().zip(viewports) {
+            if player.viewport != viewport {
+                player.viewport = viewport;
+                player.game.timed_event(TimedEvent { time: time, event: Event::Resize(viewport.2, viewport.3) });
+            }
+        }
+    }
+}
+
+/// Viewport rectangles as `(x, y, width, height)` with `y` measured from the bottom.
+fn viewports(width: u32, height: u32, players: usize) -> Vec<(u32, u32, u32, u32)> {
+    let halves = [Size::Flex(1.0), Size::Flex(1.0)];
+    let columns = gfx::distribute(width, &halves, 0);
+    let rows = gfx::distribute(height, &halves, 0);
+    let (left, right) = (columns[0], columns[1]);
+    let (top, bottom) = ((rows[1].0, rows[1].1), (rows[0].0, rows[0].1));
+    match players {
+        0 => vec![],
+        1 => vec![(0, 0, width, height)],
+        2 if width >= height => vec![(left.0, 0, left.1, height), (right.0, 0, right.1, height)],
+        2 => vec![(0, top.0, width, top.1), (0, bottom.0, width, bottom.1)],
+        3 => vec![
+            (0, top.0, width, top.1),
+            (left.0, bottom.0, left.1, bottom.1),
+            (right.0, bottom.0, right.1, bottom.1)
+        ],
+        _ => vec![
+            (left.0, top.0, left.1, top.1),
+            (right.0, top.0, right.1, top.1),
+            (left.0, bottom.0, left.1, bottom.1),
+            (right.0, bottom.0, right.1, bottom.1)
+        ]
+    }
+}
+
+impl<'a> Game for SplitScreen<'a> {
+    fn frame(&mut self, mut screen: gfx::Surface, delta: f64) {
+        if screen.get_width() != self.width || screen.get_height() != self.height {
+            let time = unsafe { ::ffi::glfwGetTime() };
+            self.relayout(screen.get_width(), screen.get_height(), time);
+        }
+        for player in &mut self.players {
+            let (x, y, w, h) = player.viewport;
+            player.game.frame(screen.subsurface(x, y, w, h), delta);
+        }
+    }
+
+    fn should_exit(&mut self) -> bool {
+        self.players.iter_mut().any(|p| p.game.should_exit())
+    }
+
+    fn event(&mut self, event: Event) {
+        let time = unsafe { ::ffi::glfwGetTime() };
+        self.timed_event(TimedEvent { time: time, event: event });
+    }
+
+    fn timed_event(&mut self, TimedEvent { time, event }: TimedEvent) {
+        self.time = time;
+        match event {
+            Event::Resize(width, height) => self.relayout(width, height, time),
+            Event::MousePos(x, y) => {
+                let (screen_height, (sx, sy)) = (self.height, self.content_scale);
+                for player in self.players.iter_mut().filter(|p| p.owns(&event)) {
+                    let (vx, vy, _, vh) = player.viewport;
+                    let top = screen_height.saturating_sub(vy + vh);
+                    let x = x as i64 - (vx as f64 / sx as f64).round() as i64;
+                    let y = y as i64 - (top as f64 / sy as f64).round() as i64;
+                    let clamp = |v: i64| v.max(i32::min_value() as i64).min(i32::max_value() as i64) as i32;
+                    player.game.timed_event(TimedEvent { time: time, event: Event::MousePos(clamp(x), clamp(y)) });
+                }
+            }
+            Event::ContentScaleChange(x, y) => {
+                self.content_scale = (x, y);
+                for player in &mut self.players {
+                    player.game.timed_event(TimedEvent { time: time, event: event.clone() });
+                }
+            }
+            Event::Close | Event::Focus | Event::Unfocus | Event::Minimize | Event::Unminimize => for player in &mut self.players {
+                player.game.timed_event(TimedEvent { time: time, event: event.clone() });
+            },
+            _ => for player in self.players.iter_mut().filter(|p| p.owns(&event)) {
+                player.game.timed_event(TimedEvent { time: time, event: event.clone() });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+
+    use { Game, Event, TimedEvent, Key, Modifiers, MouseButton, GamepadHandle, GamepadButton, gfx };
+    use super::{ viewports, SplitScreen, InputDevice };
+
+    struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+    impl Game for Recorder {
+        fn frame(&mut self, _screen: gfx::Surface, _delta: f64) {}
+
+        fn should_exit(&mut self) -> bool {
+            false
+        }
+
+        fn event(&mut self, event: Event) {
+            self.0.borrow_mut().push(event);
+        }
+    }
+
+    fn add(split: &mut SplitScreen, devices: Vec<InputDevice>) -> Rc<RefCell<Vec<Event>>> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        split.add_player(Box::new(Recorder(log.clone())), devices);
+        log
+    }
+
+    fn send(split: &mut SplitScreen, event: Event) {
+        split.timed_event(TimedEvent { time: 0.0, event: event });
+    }
+
+    fn key(k: Key) -> Event {
+        Event::KeyPress(Some(k), Modifiers::empty(), 0)
+    }
+
+    #[test]
+    fn viewports_split_along_the_longer_side() {
+        assert_eq!(viewports(800, 600, 0), vec![]);
+        assert_eq!(viewports(800, 600, 1), vec![(0, 0, 800, 600)]);
+        assert_eq!(viewports(800, 600, 2), vec![(0, 0, 400, 600), (400, 0, 400, 600)]);
+        assert_eq!(viewports(600, 800, 2), vec![(0, 400, 600, 400), (0, 0, 600, 400)]);
+        assert_eq!(viewports(800, 600, 3), vec![(0, 300, 800, 300), (0, 0, 400, 300), (400, 0, 400, 300)]);
+        assert_eq!(viewports(801, 601, 4), vec![
+            (0, 301, 401, 300), (401, 301, 400, 300),
+            (0, 0, 401, 301), (401, 0, 400, 301)
+        ]);
+    }
+
+    #[test]
+    fn input_is_routed_to_the_owning_player() {
+        let gamepad = GamepadHandle { jid: 1 };
+        let mut split = SplitScreen::new();
+        let first = add(&mut split, vec![InputDevice::Keys(vec![Key::W, Key::S]), InputDevice::Mouse]);
+        let second = add(&mut split, vec![InputDevice::Keyboard, InputDevice::Gamepad(gamepad)]);
+
+        send(&mut split, key(Key::W));
+        send(&mut split, key(Key::Up));
+        send(&mut split, Event::Character('w', Modifiers::empty()));
+        send(&mut split, Event::MousePress(MouseButton::Left, Modifiers::empty()));
+        send(&mut split, Event::GamepadPress(gamepad, GamepadButton::A));
+        send(&mut split, Event::GamepadPress(GamepadHandle { jid: 2 }, GamepadButton::A));
+        send(&mut split, Event::Focus);
+
+        assert_eq!(*first.borrow(), vec![
+            key(Key::W),
+            Event::MousePress(MouseButton::Left, Modifiers::empty()),
+            Event::Focus
+        ]);
+        assert_eq!(*second.borrow(), vec![
+            key(Key::W),
+            key(Key::Up),
+            Event::Character('w', Modifiers::empty()),
+            Event::GamepadPress(gamepad, GamepadButton::A),
+            Event::Focus
+        ]);
+    }
+
+    #[test]
+    fn adding_a_player_relayouts_immediately() {
+        let mut split = SplitScreen::new();
+        let first = add(&mut split, vec![InputDevice::Mouse]);
+        send(&mut split, Event::Resize(800, 600));
+        let second = add(&mut split, vec![InputDevice::Mouse]);
+        assert_eq!(*first.borrow(), vec![Event::Resize(800, 600), Event::Resize(400, 600)]);
+        assert_eq!(*second.borrow(), vec![Event::Resize(400, 600)]);
+
+        send(&mut split, Event::MousePos(500, 100));
+        assert_eq!(first.borrow().last(), Some(&Event::MousePos(500, 100)));
+        assert_eq!(second.borrow().last(), Some(&Event::MousePos(100, 100)));
+    }
+
+    #[test]
+    fn mouse_positions_are_relative_to_the_top_left_of_the_viewport() {
+        let mut split = SplitScreen::new();
+        send(&mut split, Event::Resize(800, 600));
+        let players: Vec<_> = (0..4).map(|_| add(&mut split, vec![InputDevice::Mouse])).collect();
+
+        send(&mut split, Event::MousePos(450, 350));
+        let last: Vec<_> = players.iter().map(|p| p.borrow().last().cloned()).collect();
+        assert_eq!(last, vec![
+            Some(Event::MousePos(450, 350)),
+            Some(Event::MousePos(50, 350)),
+            Some(Event::MousePos(450, 50)),
+            Some(Event::MousePos(50, 50))
+        ]);
+
+        send(&mut split, Event::MousePos(i32::min_value(), i32::min_value()));
+        assert_eq!(players[3].borrow().last(), Some(&Event::MousePos(i32::min_value(), i32::min_value())));
+    }
+
+    #[test]
+    fn mouse_positions_account_for_the_content_scale() {
+        let mut split = SplitScreen::new();
+        send(&mut split, Event::Resize(1600, 1200));
+        send(&mut split, Event::ContentScaleChange(2.0, 2.0));
+        let _first = add(&mut split, vec![]);
+        let second = add(&mut split, vec![InputDevice::Mouse]);
+
+        // The second player's viewport starts 800 pixels, or 400 window coordinates, from the left.
+        send(&mut split, Event::MousePos(450, 100));
+        assert_eq!(second.borrow().last(), Some(&Event::MousePos(50, 100)));
+    }
+
+    #[test]
+    fn mouse_positions_before_the_first_resize_do_not_panic() {
+        let mut split = SplitScreen::new();
+        let player = add(&mut split, vec![InputDevice::Mouse]);
+        send(&mut split, Event::MousePos(10, 20));
+        assert_eq!(*player.borrow(), vec![Event::MousePos(10, 20)]);
+    }
+}
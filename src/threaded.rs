@@ -0,0 +1,135 @@
+use std::sync::mpsc::{ self, Sender, Receiver, SyncSender, TryRecvError };
+use std::thread::{ self, JoinHandle };
+
+use ffi::*;
+use { Game, Event, TimedEvent, Configuration, gfx, launch };
+
+/// The simulation half of a game launched with `launch_threaded`. Runs on its own thread and
+/// produces a snapshot of everything the render half needs each update.
+pub trait Update : Send + 'static {
+    type Snapshot: Send + 'static;
+
+    fn update(&mut self, delta: f64) -> Self::Snapshot;
+
+    fn should_exit(&mut self) -> bool;
+
+    fn event(&mut self, event: Event);
+
+    /// Like `Game::timed_event`. By default the time is dropped and the event is passed on to
+    /// `event`.
+    fn timed_event(&mut self, event: TimedEvent) {
+        self.event(event.event);
+    }
+}
+
+/// The render half of a game launched with `launch_threaded`. Runs on the main thread, which owns
+/// the `gfx::Context`.
+pub trait Render<S> {
+    fn render(&mut self, screen: gfx::Surface, snapshot: &S);
+}
+
+struct Presenter<'a, S> {
+    renderer: Box<Render<S> + 'a>,
+    events: Option<Sender<TimedEvent>>,
+    snapshots: Option<Receiver<S>>,
+    snapshot: Option<S>,
+    thread: Option<JoinHandle<()>>,
+    exit: bool
+}
+
+impl<'a, S> Game for Presenter<'a, S> {
+    fn frame(&mut self, screen: gfx::Surface, _delta: f64) {
+        if let Some(ref snapshots) = self.snapshots {
+            loop {
+                match snapshots.try_recv() {
+                    Ok(s) => self.snapshot = Some(s),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.exit = true;
+                        break
+                    }
+                }
+            }
+        }
+        if let Some(ref snapshot) = self.snapshot {
+            self.renderer.render(screen, snapshot);
+        }
+    }
+
+    fn should_exit(&mut self) -> bool {
+        self.exit
+    }
+
+    fn event(&mut self, event: Event) {
+        let time = unsafe { glfwGetTime() };
+        self.timed_event(TimedEvent { time: time, event: event });
+    }
+
+    fn timed_event(&mut self, event: TimedEvent) {
+        if let Some(ref events) = self.events {
+            let _ = events.send(event);
+        }
+    }
+}
+
+impl<'a, S> Drop for Presenter<'a, S> {
+    fn drop(&mut self) {
+        // Disconnect both channels first so the update thread can't stay blocked on either.
+        self.events = None;
+        self.snapshots = None;
+        if let Some(thread) = self.thread.take() {
+            if let Err(e) = thread.join() {
+                if !thread::panicking() {
+                    ::std::panic::resume_unwind(e);
+                }
+            }
+        }
+    }
+}
+
+fn run_update<U: Update>(mut game: U, events: Receiver<TimedEvent>, snapshots: SyncSender<U::Snapshot>) {
+    let mut last_time = unsafe { glfwGetTime() };
+    loop {
+        loop {
+            match events.try_recv() {
+                Ok(e) => game.timed_event(e),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return
+            }
+        }
+        if game.should_exit() {
+            return;
+        }
+
+        let now = unsafe { glfwGetTime() };
+        let delta = now - last_time;
+        last_time = now;
+        if snapshots.send(game.update(delta)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Like `launch`, but runs `game` on a separate thread. Events are forwarded to it over a channel,
+/// and the main thread renders the most recent snapshot it has produced. At most one snapshot is
+/// buffered, so the update thread stays at most one frame ahead of presentation.
+pub fn launch_threaded<U, F>(config: Configuration, game: U, init: F)
+where
+    U: Update,
+    F: for<'a> FnOnce(&'a gfx::Context) -> Box<Render<U::Snapshot> + 'a>
+{
+    launch(config, move |ctx| {
+        let (event_send, event_recv) = mpsc::channel();
+        let (snapshot_send, snapshot_recv) = mpsc::sync_channel(1);
+        let renderer = init(ctx);
+        let thread = thread::spawn(move || run_update(game, event_recv, snapshot_send));
+        Box::new(Presenter {
+            renderer: renderer,
+            events: Some(event_send),
+            snapshots: Some(snapshot_recv),
+            snapshot: None,
+            thread: Some(thread),
+            exit: false
+        })
+    });
+}
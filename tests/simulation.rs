@@ -0,0 +1,56 @@
+extern crate gamelib;
+
+use gamelib::{ Game, Event, TimedEvent, MouseButton, Modifiers, Simulation, simulate, gfx };
+
+#[derive(Default)]
+struct Recorder {
+    events: Vec<TimedEvent>,
+    frames: Vec<(u32, u32, f64)>,
+    clicks: u32
+}
+
+impl Game for Recorder {
+    fn frame(&mut self, screen: gfx::Surface, delta: f64) {
+        self.frames.push((screen.get_width(), screen.get_height(), delta));
+    }
+
+    fn should_exit(&mut self) -> bool {
+        false
+    }
+
+    fn event(&mut self, _event: Event) {
+        unreachable!()
+    }
+
+    fn timed_event(&mut self, event: TimedEvent) {
+        if let Event::MousePress(MouseButton::Left, _) = event.event {
+            self.clicks += 1;
+        }
+        self.events.push(event);
+    }
+}
+
+#[test]
+#[ignore] // Needs a display to create the hidden window; the same logic is unit tested headlessly.
+fn events_are_delivered_in_order_before_the_next_frame() {
+    simulate(|ctx| {
+        let mut sim = Simulation::new(ctx, Recorder::default(), 64, 48);
+        sim.send(Event::MousePos(3, 4));
+        sim.send(Event::MousePress(MouseButton::Left, Modifiers::empty()));
+        assert!(sim.game().events.is_empty());
+
+        sim.frame(0.25);
+        assert_eq!(sim.game().clicks, 1);
+        assert_eq!(sim.game().events, vec![
+            TimedEvent { time: 0.0, event: Event::MousePos(3, 4) },
+            TimedEvent { time: 0.0, event: Event::MousePress(MouseButton::Left, Modifiers::empty()) }
+        ]);
+        assert_eq!(sim.game().frames, vec![(64, 48, 0.25)]);
+
+        sim.send(Event::Resize(32, 16));
+        sim.frame(0.5);
+        assert_eq!(sim.game().events[2], TimedEvent { time: 0.25, event: Event::Resize(32, 16) });
+        assert_eq!(sim.game().frames[1], (32, 16, 0.5));
+        assert_eq!(sim.time(), 0.75);
+    });
+}